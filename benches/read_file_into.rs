@@ -0,0 +1,45 @@
+//! Compares reading many small files with `read_file`, which allocates per file, against
+//! `read_file_into` with one reused buffer. Runs against the in-memory mock, so it measures the
+//! wrapper's allocations but not the dll's own costs.
+#![feature(test)]
+
+extern crate gfxfilemanager;
+extern crate test;
+
+use gfxfilemanager::{GFXFileManager, Mode};
+use test::Bencher;
+
+const FILE_COUNT: usize = 1000;
+const FILE_LEN: usize = 512;
+
+fn container_with_small_files() -> (GFXFileManager, Vec<String>) {
+    let file_manager = GFXFileManager::new_mock(Mode::CP);
+    assert!(file_manager.create_container("", ""));
+    let names: Vec<_> = (0..FILE_COUNT).map(|i| format!("file{}", i)).collect();
+    for name in &names {
+        file_manager.write_file(name, &[0xAB; FILE_LEN]).unwrap();
+    }
+    (file_manager, names)
+}
+
+#[bench]
+fn read_file_allocating(b: &mut Bencher) {
+    let (file_manager, names) = container_with_small_files();
+    b.iter(|| {
+        for name in &names {
+            test::black_box(file_manager.read_file(name).unwrap());
+        }
+    });
+}
+
+#[bench]
+fn read_file_into_reused_buffer(b: &mut Bencher) {
+    let (file_manager, names) = container_with_small_files();
+    let mut buf = Vec::new();
+    b.iter(|| {
+        for name in &names {
+            file_manager.read_file_into(name, &mut buf).unwrap();
+            test::black_box(&buf);
+        }
+    });
+}
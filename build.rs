@@ -1,8 +1,15 @@
+use std::env;
 use std::path::PathBuf;
 use std::fs;
 
 fn main() {
+    // the dll only exists for 32-bit windows, other targets can only use the mock
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    if target_os != "windows" || target_arch != "x86" {
+        return;
+    }
     let path = PathBuf::from("lib/");
     println!("cargo:rustc-link-search=native={}", fs::canonicalize(&path).unwrap().to_str().unwrap());
     println!("cargo:rustc-link-lib=dylib=GFXFileManager");
-}
\ No newline at end of file
+}
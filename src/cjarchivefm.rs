@@ -1,10 +1,10 @@
-use winapi::{c_char, c_uchar, c_int};
+use sys::{c_char, c_uchar, c_int};
 
 use file_manager::IFileManager;
 
 #[repr(C)]
 pub struct CJArchiveFm {//this structure needs testing as well as the functions that use it
-    destructor: extern "thiscall" fn(*mut CJArchiveFm),
+    destructor: thiscall!(fn(*mut CJArchiveFm)),
     p_filemanager: *mut IFileManager,
     h_file: c_int,
     field_0: c_int,
@@ -1,6 +1,21 @@
-use winapi::{c_char, c_int};
-use winapi::HWND;
+use std::ffi::CString;
+use std::io;
+use std::ptr::null_mut;
 
+use sys::{c_char, c_int};
+use sys::HWND;
+
+use file_manager::GFXFileManager;
+
+/// Size of the selection buffers handed to the dialog, matches MAX_PATH
+const SELECTION_BUF_LEN: usize = 260;
+
+/// Raw dialog parameters as passed to `GFXFileManager::show_dialog`
+///
+/// The dll reads `hwnd`, `mode` and `filter`, as well as the initial content of `selected_dir`.
+/// It writes the user's choice back into the `selected_dir` and `selected_file` buffers, which
+/// therefore have to point to writable memory. `unknown` is passed through untouched.
+/// The dialog has no title field, its caption is chosen by the dll.
 #[repr(C)]
 pub struct DialogData {
     pub hwnd: HWND,
@@ -10,3 +25,98 @@ pub struct DialogData {
     pub selected_file: *mut c_char,
     pub unknown: c_int,
 }
+
+/// Safe configuration for `GFXFileManager::show_dialog_for`
+pub struct DialogConfig {
+    hwnd: HWND,
+    mode: c_int,
+    filter: String,
+    initial_dir: String,
+}
+
+impl DialogConfig {
+    /// Creates a config without a parent window, in mode 0, showing every file in the root
+    pub fn new() -> Self {
+        DialogConfig {
+            hwnd: null_mut(),
+            mode: 0,
+            filter: "*".to_owned(),
+            initial_dir: String::new(),
+        }
+    }
+
+    /// The window that owns the dialog
+    pub fn hwnd(mut self, hwnd: HWND) -> Self {
+        self.hwnd = hwnd;
+        self
+    }
+
+    /// The dialog mode as understood by the dll
+    pub fn mode(mut self, mode: i32) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The pattern used to filter the displayed files
+    pub fn filter(mut self, filter: &str) -> Self {
+        self.filter = filter.to_owned();
+        self
+    }
+
+    /// The directory inside the container the dialog starts in
+    pub fn initial_dir(mut self, dir: &str) -> Self {
+        self.initial_dir = dir.to_owned();
+        self
+    }
+}
+
+impl Default for DialogConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The selection the user made in the dialog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DialogResult {
+    pub directory: String,
+    pub file: String,
+}
+
+impl GFXFileManager {
+    /// Shows the container's file dialog and returns the user's selection, or `None` if the
+    /// dialog was cancelled
+    pub fn show_dialog_for(&self, config: DialogConfig) -> io::Result<Option<DialogResult>> {
+        let to_invalid_input = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+        let filter = CString::new(config.filter).map_err(to_invalid_input)?;
+        let initial_dir = CString::new(config.initial_dir).map_err(to_invalid_input)?;
+        let initial_dir = initial_dir.as_bytes_with_nul();
+        if initial_dir.len() > SELECTION_BUF_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "initial directory is too long"));
+        }
+        let mut selected_dir = vec![0u8; SELECTION_BUF_LEN];
+        selected_dir[..initial_dir.len()].copy_from_slice(initial_dir);
+        let mut selected_file = vec![0u8; SELECTION_BUF_LEN];
+
+        let mut data = DialogData {
+            hwnd: config.hwnd,
+            mode: config.mode,
+            filter: filter.as_ptr(),
+            selected_dir: selected_dir.as_mut_ptr() as *mut c_char,
+            selected_file: selected_file.as_mut_ptr() as *mut c_char,
+            unknown: 0,
+        };
+        if self.show_dialog(&mut data) == 0 {
+            return Ok(None);
+        }
+        Ok(Some(DialogResult {
+            directory: buf_to_string(&selected_dir),
+            file: buf_to_string(&selected_file),
+        }))
+    }
+}
+
+fn buf_to_string(buf: &[u8]) -> String {
+    let len = buf.iter().position(|&x| x == 0).unwrap_or_else(|| buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
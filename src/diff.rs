@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use file_manager::{GFXFileManager, WalkEntry};
+use hash::HashAlgo;
+
+/// A difference between two containers as reported by `GFXFileManager::diff`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    /// Path of the entry relative to the container root
+    pub path: String,
+    pub is_dir: bool,
+    pub kind: DiffKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    /// The entry only exists in the other container
+    Added,
+    /// The entry only exists in this container
+    Removed,
+    /// The file exists in both containers but its content differs
+    Modified,
+}
+
+impl GFXFileManager {
+    /// Compares this container with `other` and returns the entries that were added, removed or
+    /// modified going from this container to `other`, sorted by path
+    ///
+    /// Files present in both containers count as modified if their sizes differ. Paths are
+    /// compared ignoring case like the dll does. Everything below a directory that exists on one
+    /// side only is reported as well.
+    pub fn diff(&self, other: &GFXFileManager) -> io::Result<Vec<DiffEntry>> {
+        self.diff_impl(other, false)
+    }
+
+    /// Like `diff`, but files of equal size are additionally compared by their CRC-32, which
+    /// requires reading both files completely
+    pub fn diff_contents(&self, other: &GFXFileManager) -> io::Result<Vec<DiffEntry>> {
+        self.diff_impl(other, true)
+    }
+
+    fn diff_impl(&self, other: &GFXFileManager, compare_contents: bool) -> io::Result<Vec<DiffEntry>> {
+        let mut old = entry_map(self.entries_under("\\")?);
+        let new = entry_map(other.entries_under("\\")?);
+        let mut diff = Vec::new();
+        for (key, new_entry) in new {
+            let kind = match old.remove(&key) {
+                None => Some(DiffKind::Added),
+                Some(ref old_entry) if old_entry.is_dir != new_entry.is_dir => {
+                    // a file replaced by a directory or the other way around
+                    diff.push(DiffEntry { path: old_entry.path.clone(), is_dir: old_entry.is_dir, kind: DiffKind::Removed });
+                    Some(DiffKind::Added)
+                },
+                Some(_) if new_entry.is_dir => None,
+                Some(ref old_entry) if old_entry.size != new_entry.size => Some(DiffKind::Modified),
+                Some(ref old_entry) if compare_contents => {
+                    let old_crc = self.hash_file(format!("\\{}", old_entry.path), HashAlgo::Crc32)?;
+                    let new_crc = other.hash_file(format!("\\{}", new_entry.path), HashAlgo::Crc32)?;
+                    if old_crc != new_crc { Some(DiffKind::Modified) } else { None }
+                },
+                Some(_) => None,
+            };
+            if let Some(kind) = kind {
+                diff.push(DiffEntry { path: new_entry.path, is_dir: new_entry.is_dir, kind });
+            }
+        }
+        diff.extend(old.into_iter().map(|(_, entry)| {
+            DiffEntry { path: entry.path, is_dir: entry.is_dir, kind: DiffKind::Removed }
+        }));
+        diff.sort_by(|a, b| a.path.to_lowercase().cmp(&b.path.to_lowercase()));
+        Ok(diff)
+    }
+}
+
+fn entry_map(entries: Vec<WalkEntry>) -> BTreeMap<String, WalkEntry> {
+    entries.into_iter().map(|entry| (entry.path.to_lowercase(), entry)).collect()
+}
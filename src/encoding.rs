@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+use std::io;
+use std::path::Path;
+
+use encoding_rs::{EUC_KR, UTF_8, WINDOWS_1252};
+
+use file_manager::GFXFileManager;
+
+/// The text encoding used for decoding file contents and names of a container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// Used by the korean clients
+    EucKr,
+    Windows1252,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Utf8
+    }
+}
+
+impl Encoding {
+    fn as_encoding_rs(self) -> &'static ::encoding_rs::Encoding {
+        match self {
+            Encoding::Utf8 => UTF_8,
+            Encoding::EucKr => EUC_KR,
+            Encoding::Windows1252 => WINDOWS_1252,
+        }
+    }
+
+    /// Encodes `text`, failing if it contains characters the encoding can't represent
+    pub fn encode<'a>(self, text: &'a str) -> io::Result<Cow<'a, [u8]>> {
+        let (bytes, _, had_errors) = self.as_encoding_rs().encode(text);
+        if had_errors {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} can't be represented in {:?}", text, self),
+            ));
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes `bytes` without stripping a byte order mark, replacing invalid sequences
+    pub fn decode_lossy<'a>(self, bytes: &'a [u8]) -> Cow<'a, str> {
+        self.as_encoding_rs().decode_without_bom_handling(bytes).0
+    }
+
+    /// Decodes `bytes`, stripping a leading utf8 byte order mark
+    pub fn decode<'a>(self, bytes: &'a [u8]) -> io::Result<Cow<'a, str>> {
+        let bytes = if bytes.starts_with(b"\xEF\xBB\xBF") { &bytes[3..] } else { bytes };
+        self.as_encoding_rs()
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the data is not valid {:?}", self),
+            ))
+    }
+}
+
+impl GFXFileManager {
+    /// Reads the whole file at `path` and decodes it with the manager's encoding
+    pub fn read_file_to_string<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        let data = self.read_file(path)?;
+        self.encoding().decode(&data).map(Cow::into_owned)
+    }
+}
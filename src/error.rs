@@ -0,0 +1,106 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors reported by the wrapper for calls into the dll
+#[derive(Debug)]
+pub enum GfxError {
+    /// The dll call `op` signalled failure with the given return code
+    Failed { op: &'static str, code: i32 },
+    /// An io error that occurred while working with a file
+    Io(io::Error),
+    /// The dll accepts none of the attempted object versions
+    UnsupportedVersion { attempted: Vec<i32> },
+}
+
+impl fmt::Display for GfxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GfxError::Failed { op, code } => write!(f, "{} failed with return code {}", op, code),
+            GfxError::Io(ref e) => e.fmt(f),
+            GfxError::UnsupportedVersion { ref attempted } => {
+                let versions: Vec<_> = attempted.iter().map(|v| format!("{:#x}", v)).collect();
+                write!(f, "the dll accepts none of the object versions {}", versions.join(", "))
+            },
+        }
+    }
+}
+
+impl Error for GfxError {
+    fn description(&self) -> &str {
+        match *self {
+            GfxError::Failed { .. } => "dll call failed",
+            GfxError::Io(ref e) => e.description(),
+            GfxError::UnsupportedVersion { .. } => "unsupported object version",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            GfxError::Failed { .. } | GfxError::UnsupportedVersion { .. } => None,
+            GfxError::Io(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for GfxError {
+    fn from(e: io::Error) -> Self {
+        GfxError::Io(e)
+    }
+}
+
+impl From<GfxError> for io::Error {
+    fn from(e: GfxError) -> Self {
+        match e {
+            GfxError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::Other, e),
+        }
+    }
+}
+
+/// The reasons `GFXFileManager::try_open` can fail with
+#[derive(Debug)]
+pub enum OpenError {
+    /// The container file doesn't exist
+    NotFound,
+    /// The file is a container, but the dll rejected the password
+    WrongPassword,
+    /// The file doesn't start with a valid container header
+    Corrupt,
+    /// The dll failed for another reason, carries its return code
+    Other(i32),
+}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OpenError::NotFound => f.write_str("the container file doesn't exist"),
+            OpenError::WrongPassword => f.write_str("the password of the container is wrong"),
+            OpenError::Corrupt => f.write_str("the container header is corrupt"),
+            OpenError::Other(code) => write!(f, "opening the container failed with return code {}", code),
+        }
+    }
+}
+
+impl Error for OpenError {
+    fn description(&self) -> &str {
+        match *self {
+            OpenError::NotFound => "container not found",
+            OpenError::WrongPassword => "wrong password",
+            OpenError::Corrupt => "corrupt container",
+            OpenError::Other(_) => "opening the container failed",
+        }
+    }
+}
+
+impl From<OpenError> for io::Error {
+    fn from(e: OpenError) -> Self {
+        let kind = match e {
+            OpenError::NotFound => io::ErrorKind::NotFound,
+            OpenError::WrongPassword => io::ErrorKind::PermissionDenied,
+            OpenError::Corrupt => io::ErrorKind::InvalidData,
+            OpenError::Other(_) => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, e)
+    }
+}
@@ -1,4 +1,4 @@
-use winapi::c_int;
+use sys::{c_char, c_int, c_uint, DWORD};
 
 use file_manager::IFileManager;
 use gfxinfo::GFXInfo;
@@ -9,3 +9,19 @@ extern "stdcall" {
     pub(crate) fn GFXDllReleaseObject(object: *mut IFileManager) -> c_int;
     pub(crate) fn GFXFMInfo(info: *mut GFXInfo, index: c_int) -> c_int;
 }
+
+#[link(name = "kernel32")]
+extern "stdcall" {
+    pub(crate) fn GetACP() -> c_uint;
+    pub(crate) fn SetLastError(code: DWORD);
+    pub(crate) fn WideCharToMultiByte(
+        code_page: c_uint,
+        flags: DWORD,
+        wide: *const u16,
+        wide_len: c_int,
+        out: *mut c_char,
+        out_len: c_int,
+        default_char: *const c_char,
+        used_default_char: *mut c_int,
+    ) -> c_int;
+}
@@ -0,0 +1,142 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, SeekFrom};
+
+use file_manager::{Access, GFXFileManager};
+use gfxfile::File;
+use path;
+
+/// Keeps recently used files open while capping the number of open handles, see
+/// `GFXFileManager::with_max_open_files`
+///
+/// Files are looked up by their normalized path, so `a/b` and `a\\b` share a handle. When the cap
+/// is exceeded the least recently used file is dropped, which closes its handle; requesting it
+/// again reopens it at the position it had when it was dropped.
+pub struct FileCache<'a> {
+    file_manager: &'a GFXFileManager,
+    max_open_files: usize,
+    /// Open files ordered from least to most recently used
+    files: VecDeque<(String, File<'a>)>,
+    /// Positions of the files that were closed to stay below the cap
+    positions: HashMap<String, u64>,
+}
+
+impl<'a> FileCache<'a> {
+    /// Returns the file at `path`, opening it with `Access::OpenExisting` if it isn't open yet
+    ///
+    /// A file that was closed to stay below the cap is reopened at its previous position. A file
+    /// whose container has been closed in the meantime is reopened at the start.
+    pub fn get(&mut self, path: &str) -> io::Result<&mut File<'a>> {
+        let key = path::normalize(path).into_owned();
+        let cached = self.files.iter().position(|&(ref p, _)| *p == key);
+        let entry = match cached {
+            Some(i) => {
+                let entry = self.files.remove(i).unwrap();
+                if entry.1.is_stale() { None } else { Some(entry) }
+            },
+            None => None,
+        };
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                while self.files.len() >= self.max_open_files {
+                    let (evicted, file) = self.files.pop_front().unwrap();
+                    if let Ok(pos) = file.seek64(SeekFrom::Current(0)) {
+                        self.positions.insert(evicted, pos);
+                    }
+                }
+                let file = self.file_manager.open_file(&key, Access::OpenExisting, 0)?;
+                if let Some(pos) = self.positions.remove(&key) {
+                    file.seek64(SeekFrom::Start(pos))?;
+                }
+                (key, file)
+            },
+        };
+        self.files.push_back(entry);
+        Ok(&mut self.files.back_mut().unwrap().1)
+    }
+
+    /// Closes the file at `path` if it is open and forgets its position, returns whether it was
+    /// open
+    pub fn remove(&mut self, path: &str) -> bool {
+        let key = path::normalize(path);
+        self.positions.remove(&*key);
+        match self.files.iter().position(|&(ref p, _)| *p == key) {
+            Some(i) => {
+                self.files.remove(i);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Closes all cached files and forgets their positions
+    pub fn clear(&mut self) {
+        self.files.clear();
+        self.positions.clear();
+    }
+
+    /// Returns the number of files currently held open
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn max_open_files(&self) -> usize {
+        self.max_open_files
+    }
+}
+
+impl GFXFileManager {
+    /// Creates a cache that holds at most `n` files of this manager open at once, closing the
+    /// least recently used one when another file is requested
+    ///
+    /// `n` is raised to 1 if it is 0.
+    pub fn with_max_open_files(&self, n: usize) -> FileCache {
+        FileCache {
+            file_manager: self,
+            max_open_files: n.max(1),
+            files: VecDeque::new(),
+            positions: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::io::Read;
+
+    use file_manager::{GFXFileManager, Mode};
+
+    #[test]
+    fn evicted_files_are_reopened_at_their_position() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager.write_file("a", b"abcd").unwrap();
+        file_manager.write_file("b", b"efgh").unwrap();
+        let mut cache = file_manager.with_max_open_files(1);
+        let mut buf = [0u8; 2];
+        cache.get("a").unwrap().read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ab");
+        cache.get("b").unwrap().read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ef");
+        cache.get("a").unwrap().read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"cd");
+        assert_eq!(file_manager.open_file_count(), 1);
+    }
+
+    #[test]
+    fn paths_are_normalized() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager.create_directory("dir").unwrap();
+        file_manager.write_file("dir\\file", b"data").unwrap();
+        let mut cache = file_manager.with_max_open_files(2);
+        cache.get("dir/file").unwrap();
+        cache.get("dir\\file").unwrap();
+        assert_eq!(cache.len(), 1);
+        assert!(cache.remove("dir//file"));
+    }
+}
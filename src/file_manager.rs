@@ -1,37 +1,179 @@
-use std::ffi::{CString, CStr};
+use std::cell::Cell;
+use std::ffi::{CString, CStr, OsString};
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor, Read, Write};
+use std::path::Path;
 use std::ptr::null_mut;
-use std::string::FromUtf8Error;
 use std::convert::TryFrom;
 
-use winapi::{c_char, c_int, c_long, c_ulong, c_void};
-use winapi::{DWORD, HMODULE, HWND, LPDWORD, LPFILETIME};
+use sys::{c_char, c_int, c_long, c_ulong, c_void};
+use sys::{DWORD, HMODULE, HWND, LPDWORD, LPFILETIME};
 
-use ffi::{GFXDllCreateObject, GFXDllReleaseObject};
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
+use ffi::{GFXDllCreateObject, GFXDllReleaseObject, GetACP, SetLastError, WideCharToMultiByte};
 
 use cjarchivefm::CJArchiveFm;
 use dialog::DialogData;
+use encoding::Encoding;
+use error::{GfxError, OpenError};
 use gfxfile::File;
+use glob::translate_glob;
+#[cfg(feature = "mock")]
+use mock::MockFileManager;
 use result_entry::ResultEntry;
 use search_result::{SearchResult, GFXSearchResult};
-
-const OBJECT_VERSION: c_int = 0x1007;
+use unwind::PanicSlot;
+
+pub(crate) const OBJECT_VERSION: c_int = 0x1007;
+/// The object versions known to work with this crate, newest first
+///
+/// 0x1007 is the only version identified so far, so `negotiate` has nothing to fall back to yet
+/// and only verifies that the loaded dll accepts it. Versions of other dll releases go here once
+/// they are known.
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
+const KNOWN_VERSIONS: &[c_int] = &[OBJECT_VERSION];
+/// The handle `open_file` and `create_file` return when the dll couldn't open the file, the same
+/// value as windows' `INVALID_HANDLE_VALUE`
+pub const INVALID_HANDLE: c_int = -1;
+/// Every pk2 container starts with this signature
+const PK2_SIGNATURE: &[u8] = b"JoyMax File Manager!\n";
+/// Size of the buffer used when streaming files out of the container
+pub(crate) const STREAM_BUF_LEN: usize = 0x1_0000;
+/// Initial size of the buffers handed to the dll for string getters, doubled on truncation
+const PATH_BUF_LEN: usize = 256;
+/// Size of the buffer handed to `get_virtual_path`, matches MAX_PATH
+pub(crate) const VIRTUAL_PATH_BUF_LEN: usize = 260;
+/// Upper bound for the grow-and-retry loop so a misbehaving dll can't make us allocate forever
+const MAX_PATH_BUF_LEN: usize = 0x1_0000;
 
 macro_rules! cstring {
     ($str: expr) => { CString::new($str).unwrap() };
 }
 
+/// Calls the vtable entry `$name`, the values after the `;` describe the call in the log and are
+/// only evaluated with the `log` feature
+#[cfg(not(feature = "log"))]
 macro_rules! vtable_call {
     ($_self:ident, $name:ident$(, $arg:expr)*) => {
         unsafe { ((*(*$_self._file_manager).vtable).$name)($_self._file_manager, $($arg),*) }
     };
+    ($_self:ident, $name:ident$(, $arg:expr)*; $($log:expr),+) => {
+        vtable_call!($_self, $name$(, $arg)*)
+    };
 }
 
-pub type ForEachCallback = extern "cdecl" fn(CallbackState, ResultEntry, *mut c_void) -> ();
-pub type ErrorHandler = extern "cdecl" fn(HWND, *const c_char, *const c_char) -> c_int;
+/// Traces every call with the values given after the `;` and the returned value
+#[cfg(feature = "log")]
+macro_rules! vtable_call {
+    ($_self:ident, $name:ident$(, $arg:expr)*) => {{
+        let res = unsafe { ((*(*$_self._file_manager).vtable).$name)($_self._file_manager, $($arg),*) };
+        trace!("{} returned {:?}", stringify!($name), res);
+        res
+    }};
+    ($_self:ident, $name:ident$(, $arg:expr)*; $($log:expr),+) => {{
+        trace!("{}({})", stringify!($name), [$(format!("{:?}", $log)),+].join(", "));
+        vtable_call!($_self, $name$(, $arg)*)
+    }};
+}
 
-extern "cdecl" fn err_dummy(_: HWND, _: *const c_char, _: *const c_char) -> c_int { 1 }
+/// Like `vtable_call!` for functions returning zero on failure, evaluates to an `io::Result<()>`
+/// carrying the os error the dll set, which is read right after the call
+#[cfg(not(feature = "log"))]
+macro_rules! vtable_call_os {
+    ($_self:ident, $name:ident$(, $arg:expr)*; $($log:expr),+) => {{
+        clear_last_error();
+        let res = unsafe { ((*(*$_self._file_manager).vtable).$name)($_self._file_manager, $($arg),*) };
+        os_result(stringify!($name), res)
+    }};
+}
+
+/// Traces the call like `vtable_call!`, the os error is read before tracing the result
+#[cfg(feature = "log")]
+macro_rules! vtable_call_os {
+    ($_self:ident, $name:ident$(, $arg:expr)*; $($log:expr),+) => {{
+        trace!("{}({})", stringify!($name), [$(format!("{:?}", $log)),+].join(", "));
+        clear_last_error();
+        let res = unsafe { ((*(*$_self._file_manager).vtable).$name)($_self._file_manager, $($arg),*) };
+        let res = os_result(stringify!($name), res);
+        trace!("{} returned {:?}", stringify!($name), res);
+        res
+    }};
+}
+
+/// The callback invoked by `for_each_entry_in_container`, the first argument is the raw
+/// `CallbackState`
+pub type ForEachCallback = extern "C" fn(c_int, ResultEntry, *mut c_void) -> ();
+pub type ErrorHandler = extern "C" fn(HWND, *const c_char, *const c_char) -> c_int;
+
+extern "C" fn err_dummy(_: HWND, _: *const c_char, _: *const c_char) -> c_int { 1 }
+
+extern "C" fn for_each_trampoline<F>(state: c_int, entry: ResultEntry, userstate: *mut c_void)
+    where F: FnMut(CallbackState, &ResultEntry, &str)
+{
+    // skip states we don't know instead of handing out an invalid enum
+    if let Ok(state) = CallbackState::try_from(state) {
+        let for_each = unsafe { &mut *(userstate as *mut ForEachState<F>) };
+        let visitor = &mut for_each.visitor;
+        for_each.panic.call(|| visitor.visit(state, &entry));
+    }
+}
+
+/// The userstate of `for_each`
+struct ForEachState<F> {
+    visitor: EntryVisitor<F>,
+    /// A panic of the callback, resumed after the dll returned
+    panic: PanicSlot,
+}
+
+/// Keeps track of the path of the visited entry
+struct EntryVisitor<F> {
+    callback: F,
+    /// The manager's encoding, used to decode the entries' names
+    encoding: Encoding,
+    path: String,
+    /// Lengths of `path` before entering each of the currently entered directories
+    dirs: Vec<usize>,
+}
+
+impl<F> EntryVisitor<F>
+    where F: FnMut(CallbackState, &ResultEntry, &str)
+{
+    /// The dll reports a directory with `EnterDir` before its content and with `LeaveDir` after
+    /// it, so the directory's name is pushed on enter and popped again on leave
+    fn visit(&mut self, state: CallbackState, entry: &ResultEntry) {
+        match state {
+            CallbackState::EnterDir => {
+                self.dirs.push(self.path.len());
+                self.push_name(entry);
+                (self.callback)(state, entry, &self.path);
+            },
+            CallbackState::LeaveDir => {
+                (self.callback)(state, entry, &self.path);
+                if let Some(len) = self.dirs.pop() {
+                    self.path.truncate(len);
+                }
+            },
+            CallbackState::File => {
+                let len = self.path.len();
+                self.push_name(entry);
+                (self.callback)(state, entry, &self.path);
+                self.path.truncate(len);
+            },
+            CallbackState::Init => (self.callback)(state, entry, &self.path),
+        }
+    }
+
+    fn push_name(&mut self, entry: &ResultEntry) {
+        if !self.path.is_empty() {
+            self.path.push('\\');
+        }
+        self.path.push_str(&entry.name_in(self.encoding));
+    }
+}
 
 #[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CallbackState {
     Init = 0,
     EnterDir = 1,
@@ -39,10 +181,42 @@ pub enum CallbackState {
     File = 3
 }
 
+impl TryFrom<c_int> for CallbackState {
+    type Error = ();
+    fn try_from(state: c_int) -> Result<Self, Self::Error> {
+        match state {
+            0 => Ok(CallbackState::Init),
+            1 => Ok(CallbackState::EnterDir),
+            2 => Ok(CallbackState::LeaveDir),
+            3 => Ok(CallbackState::File),
+            _ => Err(())
+        }
+    }
+}
+
+/// A pair of integers the dll stores through `set_unknown` and hands back through `get_unknown`,
+/// their meaning hasn't been identified yet
 #[repr(C)]
-pub struct UnknownPair(c_int, c_int);
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UnknownPair(pub c_int, pub c_int);
+
+/// The handle of the module the dll considers the main module
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ModuleHandle(pub HMODULE);
+
+impl ModuleHandle {
+    pub fn as_raw(&self) -> HMODULE {
+        self.0
+    }
+}
+
+impl fmt::Debug for ModuleHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ModuleHandle({:p})", self.0)
+    }
+}
 
-#[allow(overflowing_literals)]
+#[repr(u32)]
 pub enum Access {
     OpenExisting = 0,
     ShareRead = 0x8000_0000,
@@ -79,20 +253,146 @@ impl TryFrom<i32> for Mode {
 }
 
 pub struct GFXFileManager {
-    _file_manager: *mut IFileManager
+    _file_manager: *mut IFileManager,
+    /// Bumped whenever the container is opened, created or closed, so files of a previous
+    /// container can tell that their handle is gone
+    generation: Cell<u32>,
+    encoding: Cell<Encoding>,
+    read_only: Cell<bool>,
+    /// Number of currently alive `File`s
+    open_files: Cell<usize>,
+    /// The object version the dll object was created with
+    version: c_int,
+    /// Owns the in-memory fake `_file_manager` points to when created through `new_mock`
+    #[cfg(feature = "mock")]
+    _mock: Option<Box<MockFileManager>>,
 }
 
 impl GFXFileManager {
-    pub fn new(mode: Mode) -> Self {
-        Self::new_with_version(mode, OBJECT_VERSION)
+    /// Creates a manager, failing with `GfxError::UnsupportedVersion` if the loaded dll doesn't
+    /// accept any known object version, see `negotiate`
+    #[cfg(all(target_os = "windows", target_arch = "x86"))]
+    pub fn new(mode: Mode) -> Result<Self, GfxError> {
+        Self::negotiate(mode)
     }
 
+    /// Creates a manager with the given object version without checking whether the dll accepts
+    /// it
+    #[cfg(all(target_os = "windows", target_arch = "x86"))]
     pub fn new_with_version(mode: Mode, version: c_int) -> Self {
+        Self::from_ptr(IFileManager::new_ptr(mode as i32, version), version)
+    }
+
+    /// Creates a manager with the newest of the known object versions the loaded dll accepts
+    ///
+    /// The versions are tried newest first and checked with `check_version`. Only one version is
+    /// known so far, so this currently just verifies that the dll accepts it. Fails with
+    /// `GfxError::UnsupportedVersion` listing the attempted versions if none is accepted.
+    #[cfg(all(target_os = "windows", target_arch = "x86"))]
+    pub fn negotiate(mode: Mode) -> Result<Self, GfxError> {
+        let mode = mode as c_int;
+        for &version in KNOWN_VERSIONS {
+            let ptr = IFileManager::new_ptr(mode, version);
+            if ptr.is_null() {
+                continue;
+            }
+            let file_manager = Self::from_ptr(ptr, version);
+            if file_manager.check_version(version) != 0 {
+                return Ok(file_manager);
+            }
+        }
+        Err(GfxError::UnsupportedVersion { attempted: KNOWN_VERSIONS.to_vec() })
+    }
+
+    #[cfg(all(target_os = "windows", target_arch = "x86"))]
+    fn from_ptr(file_manager: *mut IFileManager, version: c_int) -> Self {
+        Self {
+            _file_manager: file_manager,
+            generation: Cell::new(0),
+            encoding: Cell::new(Encoding::default()),
+            read_only: Cell::new(false),
+            open_files: Cell::new(0),
+            version,
+            #[cfg(feature = "mock")]
+            _mock: None,
+        }
+    }
+
+    /// Creates a manager and opens the container `filename`, failing instead of returning a
+    /// manager without an open container
+    #[cfg(all(target_os = "windows", target_arch = "x86"))]
+    pub fn open(filename: &str, password: &str, mode: Mode) -> io::Result<Self> {
+        let file_manager = Self::new(mode)?;
+        file_manager.try_open(filename, password, 0)?;
+        Ok(file_manager)
+    }
+
+    /// Creates a manager and a new container `filename`, failing instead of returning a manager
+    /// without an open container
+    #[cfg(all(target_os = "windows", target_arch = "x86"))]
+    pub fn create(filename: &str, password: &str, mode: Mode) -> io::Result<Self> {
+        let file_manager = Self::new(mode)?;
+        if !file_manager.create_container(filename, password) {
+            return Err(io::Error::new(io::ErrorKind::Other, format!("failed to create {}", filename)));
+        }
+        Ok(file_manager)
+    }
+
+    /// Creates a manager backed by an in-memory fake of the dll instead of the real one
+    ///
+    /// The fake keeps its files in a `HashMap` and supports containers, files, directories and the
+    /// search and traversal functions, which is enough to test code built on this crate without
+    /// the dll being present. Functions it doesn't support report failure.
+    #[cfg(feature = "mock")]
+    pub fn new_mock(mode: Mode) -> Self {
+        let mut mock = MockFileManager::new(mode as i32);
         Self {
-            _file_manager: IFileManager::new_ptr(mode as i32, version)
+            _file_manager: mock.as_ptr(),
+            generation: Cell::new(0),
+            encoding: Cell::new(Encoding::default()),
+            read_only: Cell::new(false),
+            open_files: Cell::new(0),
+            version: OBJECT_VERSION,
+            _mock: Some(mock),
         }
     }
 
+    /// Makes every read of the mock after the next `n` ones fail
+    #[cfg(all(test, feature = "mock"))]
+    pub(crate) fn mock_fail_reads_after(&self, n: usize) {
+        MockFileManager::fail_reads_after(self._file_manager, n);
+    }
+
+    /// Creates a manager with a fresh container that only lives in memory
+    ///
+    /// The dll has no memory-backed containers, so this uses the in-memory fake of `new_mock`,
+    /// which offers the same read, write and search surface as a real container. The content can't
+    /// be serialized into the pk2 format since its encryption is only implemented by the dll, copy
+    /// the files into a container opened by the dll instead to persist them.
+    #[cfg(feature = "mock")]
+    pub fn create_memory_container(password: &str) -> io::Result<Self> {
+        let file_manager = Self::new_mock(Mode::CP);
+        if !file_manager.create_container("", password) {
+            return Err(io::Error::new(io::ErrorKind::Other, "failed to create the container"));
+        }
+        Ok(file_manager)
+    }
+
+    /// Returns the object version the manager was created with
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Returns the encoding used to decode file contents and names, utf8 by default
+    pub fn encoding(&self) -> Encoding {
+        self.encoding.get()
+    }
+
+    /// Sets the encoding used to decode file contents and names
+    pub fn set_encoding(&self, encoding: Encoding) {
+        self.encoding.set(encoding);
+    }
+
     pub fn disable_err_msg_box(&self) {
         self.register_error_handler(err_dummy);
     }
@@ -105,13 +405,25 @@ impl GFXFileManager {
     }
 
     /// Sets some configuration
-    pub fn config_set(&self, i1: i32, i2: i32) -> i32 {
-        vtable_call!(self, config_set, i1, i2)
+    ///
+    /// # Safety
+    ///
+    /// The keys the dll accepts haven't been identified yet, like for `config_get` the caller has
+    /// to make sure the key is valid for the loaded dll.
+    #[allow(unused_unsafe)]
+    pub unsafe fn config_set(&self, i1: i32, i2: i32) -> i32 {
+        vtable_call!(self, config_set, i1, i2; i1, i2)
     }
 
-    /// Gets some configuration, most likely just crashes the application atm
-    pub fn config_get(&self, i1: i32, i2: i32) -> i32 {
-        vtable_call!(self, config_get, i1, i2)
+    /// Gets some configuration
+    ///
+    /// # Safety
+    ///
+    /// The keys the dll accepts haven't been identified yet and calling this with an unknown key
+    /// crashes the application, so the caller has to make sure the key is valid for the loaded dll.
+    #[allow(unused_unsafe)]
+    pub unsafe fn config_get(&self, i1: i32, i2: i32) -> i32 {
+        vtable_call!(self, config_get, i1, i2; i1, i2)
     }
 
     /// Creates a new container and opens it
@@ -123,7 +435,8 @@ impl GFXFileManager {
     pub fn create_container(&self, filename: &str, password: &str) -> bool {
         let filename = cstring!(filename);
         let password = cstring!(password);
-        vtable_call!(self, create_container, filename.as_ptr(), password.as_ptr()) != 0
+        self.next_generation();
+        vtable_call!(self, create_container, filename.as_ptr(), password.as_ptr(); filename.to_string_lossy()) != 0
     }
 
     /// Opens an existing container
@@ -136,14 +449,145 @@ impl GFXFileManager {
     pub fn open_container(&self, filename: &str, password: &str, mode: i32) -> bool {
         let filename = cstring!(filename);
         let password = cstring!(password);
-        vtable_call!(self, open_container, filename.as_ptr(), password.as_ptr(), mode) != 0
+        self.next_generation();
+        vtable_call!(self, open_container, filename.as_ptr(), password.as_ptr(), mode; filename.to_string_lossy(), mode) != 0
+    }
+
+    /// Opens an existing container like `open_container`, but tells apart why opening failed
+    ///
+    /// The dll only reports success or failure, so the reason is inferred: a missing file is
+    /// `NotFound`, a file that doesn't start with the pk2 signature is `Corrupt`, and a file with a
+    /// valid signature that the dll still refuses most likely has a different password.
+    pub fn try_open(&self, filename: &str, password: &str, mode: i32) -> Result<(), OpenError> {
+        self.next_generation();
+        let c_filename = cstring!(filename);
+        let c_password = cstring!(password);
+        let res = vtable_call!(self, open_container, c_filename.as_ptr(), c_password.as_ptr(), mode; c_filename.to_string_lossy(), mode);
+        if res != 0 {
+            return Ok(());
+        }
+        let mut header = vec![0u8; PK2_SIGNATURE.len()];
+        match fs::File::open(filename).and_then(|mut file| file.read_exact(&mut header)) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Err(OpenError::NotFound),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(OpenError::Corrupt),
+            Err(_) => Err(OpenError::Other(res)),
+            Ok(()) if &header[..] != PK2_SIGNATURE => Err(OpenError::Corrupt),
+            Ok(()) => Err(OpenError::WrongPassword),
+        }
     }
 
     /// Closes the current container
+    ///
+    /// Files that are still alive become detached, dropping them won't close their stale handles.
     pub fn close_container(&self) -> bool {
+        self.next_generation();
         vtable_call!(self, close_container) != 0
     }
 
+    /// Opens an existing container for reading only
+    ///
+    /// No read-only mode of the dll is known, so the container is opened with mode 0 like `open`
+    /// does and the dll itself would still allow writes. Only the wrapper guards against them: the
+    /// manager remembers that the container is read-only and refuses to create, write or delete
+    /// files and directories until another container is opened.
+    pub fn open_container_readonly(&self, filename: &str, password: &str) -> io::Result<()> {
+        if !self.open_container(filename, password, 0) {
+            return Err(io::Error::new(io::ErrorKind::Other, format!("failed to open {}", filename)));
+        }
+        self.read_only.set(true);
+        Ok(())
+    }
+
+    /// Returns true if the current container was opened with `open_container_readonly`
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.get()
+    }
+
+    /// Converts a container path into the form the dll expects: separators are normalized to `\`
+    /// and the path is encoded with the manager's encoding
+    pub(crate) fn c_path<P: AsRef<Path>>(&self, path: P) -> io::Result<CString> {
+        let path = path.as_ref();
+        let path = path.to_str().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not valid unicode", path.display()))
+        })?;
+        let path = ::path::normalize(path);
+        let bytes = self.encoding().encode(&path)?;
+        CString::new(bytes.into_owned()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// Resolves a container path against the current directory into a path from the root
+    ///
+    /// Both separators are accepted and `.` and `..` components are resolved, so two paths naming
+    /// the same entry resolve to the same string, up to case.
+    pub(crate) fn absolute_path<P: AsRef<Path>>(&self, path: P) -> io::Result<String> {
+        let path = path.as_ref().to_string_lossy();
+        let path = if path.starts_with(|c| c == '\\' || c == '/') {
+            path.into_owned()
+        } else {
+            ::path::join(&self.get_directory_name()?, &path)
+        };
+        let mut parts = Vec::new();
+        for part in path.split(|c| c == '\\' || c == '/') {
+            match part {
+                "" | "." => (),
+                ".." => { parts.pop(); },
+                part => parts.push(part),
+            }
+        }
+        Ok(format!("{}{}", ::path::SEPARATOR, parts.join("\\")))
+    }
+
+    pub(crate) fn check_writable(&self) -> io::Result<()> {
+        if self.is_read_only() {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, "the container is opened read-only"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Closes the current container, if any, and opens another one while keeping the manager's
+    /// configuration like the hwnd and the registered error handler
+    ///
+    /// If opening the new container fails, no container is left open.
+    pub fn switch_container(&self, filename: &str, password: &str, mode: i32) -> io::Result<()> {
+        if self.is_open() && !self.close_container() {
+            return Err(io::Error::new(io::ErrorKind::Other, "failed to close the current container"));
+        }
+        if !self.open_container(filename, password, mode) {
+            if self.is_open() {
+                self.close_container();
+            }
+            return Err(io::Error::new(io::ErrorKind::Other, format!("failed to open {}", filename)));
+        }
+        Ok(())
+    }
+
+    /// Returns the number of `File`s of this manager that are currently alive
+    ///
+    /// Handles are only closed when their `File` is dropped, so this helps detecting handles that
+    /// are kept alive longer than intended.
+    pub fn open_file_count(&self) -> usize {
+        self.open_files.get()
+    }
+
+    pub(crate) fn file_opened(&self) {
+        self.open_files.set(self.open_files.get() + 1);
+    }
+
+    pub(crate) fn file_closed(&self) {
+        self.open_files.set(self.open_files.get() - 1);
+    }
+
+    /// Returns the generation of the currently open container, see `File::drop`
+    pub(crate) fn generation(&self) -> u32 {
+        self.generation.get()
+    }
+
+    fn next_generation(&self) {
+        self.read_only.set(false);
+        self.generation.set(self.generation.get().wrapping_add(1));
+    }
+
     /// Returns true if a container is currently open
     pub fn is_open(&self) -> bool {
         vtable_call!(self, is_open) != 0
@@ -156,12 +600,12 @@ impl GFXFileManager {
     }
 
     /// Returns the MainModule-handle
-    pub fn main_module_handle(&self) -> HMODULE {
-        vtable_call!(self, main_module_handle)
+    pub fn main_module_handle(&self) -> ModuleHandle {
+        ModuleHandle(vtable_call!(self, main_module_handle))
     }
 
     pub fn function_9(&self, i1: i32) -> i32 {
-        vtable_call!(self, function_9, i1)
+        vtable_call!(self, function_9, i1; i1)
     }
 
     /// Opens a file inside the container using a path and returns a File object
@@ -170,16 +614,80 @@ impl GFXFileManager {
     ///
     /// * filename - Filename, relative to current dir or absolute path inside archive
     /// * unknown - Not used for original CPFileManager
-    pub fn open_file(&self, filename: &str, access: Access, unknown: i32) -> ::std::io::Result<File> {
-        let filename = cstring!(filename);
-        let res = vtable_call!(self, open_file, filename.as_ptr(), access as i32, unknown);
-        if res == -1 {
-            Err(::std::io::Error::new(::std::io::ErrorKind::NotFound, ""))
+    ///
+    /// `Access::CreateAlways` creates or truncates the file, so it fails with `PermissionDenied`
+    /// without calling the dll if the container is read-only.
+    pub fn open_file<P: AsRef<Path>>(&self, filename: P, access: Access, unknown: i32) -> io::Result<File> {
+        if let Access::CreateAlways = access {
+            self.check_writable()?;
+        }
+        let c_filename = self.c_path(&filename)?;
+        let access = access as i32;
+        let res = vtable_call!(self, open_file, c_filename.as_ptr(), access, unknown; c_filename.to_string_lossy(), access, unknown);
+        if res == INVALID_HANDLE {
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("failed to open {}", filename.as_ref().display())))
         } else {
             Ok(File::new(self, res))
         }
     }
 
+    /// Opens an existing file inside the container with the cursor placed at its end, so that
+    /// subsequent writes append to it
+    ///
+    /// The dll has no append flag, the file is opened with `Access::OpenExisting` and then seeked
+    /// to its end.
+    pub fn open_append<P: AsRef<Path>>(&self, filename: P) -> io::Result<File> {
+        let file = self.open_file(filename, Access::OpenExisting, 0)?;
+        file.seek_to_end()?;
+        Ok(file)
+    }
+
+    /// Reads the whole file at `path` into a newly allocated vector
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.read_file_into(path, &mut data)?;
+        Ok(data)
+    }
+
+    /// Reads the whole file at `path` into memory and returns a cursor over it that can be seeked
+    /// and read repeatedly without calling into the dll again
+    ///
+    /// Writing to the cursor only changes the copy in memory, not the file in the container.
+    pub fn open_cursor<P: AsRef<Path>>(&self, path: P) -> io::Result<Cursor<Vec<u8>>> {
+        self.read_file(path).map(Cursor::new)
+    }
+
+    /// Replaces the content of `buf` with the whole file at `path`
+    ///
+    /// The capacity of `buf` is kept, so reusing the same buffer for many files avoids an
+    /// allocation per file.
+    pub fn read_file_into<P: AsRef<Path>>(&self, path: P, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.clear();
+        let mut file = self.open_file(path, Access::OpenExisting, 0)?;
+        let size = file.try_len()?;
+        buf.reserve(size as usize);
+        file.read_to_end(buf).map(|_| ())
+    }
+
+    /// Opens the file at `path` and feeds its content chunk by chunk into `sink`, using a fixed
+    /// size buffer so memory stays bounded regardless of the file size. Returns the number of
+    /// bytes read.
+    pub(crate) fn stream_file<P, F>(&self, path: P, mut sink: F) -> io::Result<u64>
+        where P: AsRef<Path>, F: FnMut(&[u8]) -> io::Result<()>
+    {
+        let mut file = self.open_file(path, Access::OpenExisting, 0)?;
+        let mut buf = vec![0u8; STREAM_BUF_LEN];
+        let mut total = 0;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                return Ok(total);
+            }
+            sink(&buf[..n])?;
+            total += n as u64;
+        }
+    }
+
     /// Opens a file inside the container using the CJArchiveFm-class and returns a File object
     ///
     /// # Arguments
@@ -187,9 +695,15 @@ impl GFXFileManager {
     /// * fm - A mutable reference to a CJArchiveFm
     /// * filename - Filename, relative to current dir or absolute path inside archive
     /// * unknown - not used for original CPFileManager
-    pub fn open_file_cj(&self, fm: &mut CJArchiveFm, filename: &str, access: Access, unknown: i32) -> File {
-        let filename = cstring!(filename);
-        File::new(self, vtable_call!(self, open_file_cj, fm, filename.as_ptr(), access as i32, unknown))
+    pub fn open_file_cj<P: AsRef<Path>>(&self, fm: &mut CJArchiveFm, filename: P, access: Access, unknown: i32) -> io::Result<File> {
+        let c_filename = self.c_path(&filename)?;
+        let access = access as i32;
+        let res = vtable_call!(self, open_file_cj, fm, c_filename.as_ptr(), access, unknown; c_filename.to_string_lossy(), access, unknown);
+        if res == INVALID_HANDLE {
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("failed to open {}", filename.as_ref().display())))
+        } else {
+            Ok(File::new(self, res))
+        }
     }
 
     pub fn function_12(&self) -> i32 {
@@ -206,12 +720,18 @@ impl GFXFileManager {
     ///
     /// * filename - Filename, relative to current dir or absolute path inside archive
     /// * unknown
-    pub fn create_file(&self, filename: &str, unknown: i32) -> File {
-        let filename = cstring!(filename);
-        File::new(self, vtable_call!(self, create_file, filename.as_ptr(), unknown))
+    pub fn create_file<P: AsRef<Path>>(&self, filename: P, unknown: i32) -> io::Result<File> {
+        self.check_writable()?;
+        let c_filename = self.c_path(&filename)?;
+        let res = vtable_call!(self, create_file, c_filename.as_ptr(), unknown; c_filename.to_string_lossy(), unknown);
+        if res == INVALID_HANDLE {
+            Err(io::Error::new(io::ErrorKind::Other, format!("failed to create {}", filename.as_ref().display())))
+        } else {
+            self.update_current_directory();
+            Ok(File::new(self, res))
+        }
     }
 
-
     /// Creates a file inside the container using the CJArchiveFm-class and returns a File object
     ///
     /// # Arguments
@@ -219,60 +739,183 @@ impl GFXFileManager {
     /// * fm - A mutable reference to a CJArchiveFm
     /// * filename - Filename, relative to current dir or absolute path inside archive
     /// * unknown
-    pub fn create_file_cj(&self, fm: &mut CJArchiveFm, filename: &str, unknown: i32) -> File {
-        let filename = cstring!(filename);
-        File::new(self, vtable_call!(self, create_file_cj, fm, filename.as_ptr(), unknown))
+    ///
+    /// Fails with `PermissionDenied` without calling the dll if the container is read-only.
+    pub fn create_file_cj<P: AsRef<Path>>(&self, fm: &mut CJArchiveFm, filename: P, unknown: i32) -> io::Result<File> {
+        self.check_writable()?;
+        let c_filename = self.c_path(&filename)?;
+        let res = vtable_call!(self, create_file_cj, fm, c_filename.as_ptr(), unknown; c_filename.to_string_lossy(), unknown);
+        if res == INVALID_HANDLE {
+            Err(io::Error::new(io::ErrorKind::Other, format!("failed to create {}", filename.as_ref().display())))
+        } else {
+            self.update_current_directory();
+            Ok(File::new(self, res))
+        }
     }
 
     /// Deletes a file by name
-    pub fn delete_file(&self, filename: &str) -> i32 {
-        let filename = cstring!(filename);
-        vtable_call!(self, delete_file, filename.as_ptr())
+    ///
+    /// Fails with `PermissionDenied` without calling the dll if the container is read-only.
+    pub fn delete_file<P: AsRef<Path>>(&self, filename: P) -> io::Result<()> {
+        self.check_writable()?;
+        let filename = self.c_path(filename)?;
+        match vtable_call!(self, delete_file, filename.as_ptr(); filename.to_string_lossy()) {
+            0 => Err(GfxError::Failed { op: "delete_file", code: 0 }.into()),
+            _ => {
+                self.update_current_directory();
+                Ok(())
+            },
+        }
+    }
+
+    /// Deletes every file in `paths`, continuing past failures, and returns the outcome for each
+    /// path in input order
+    ///
+    /// A read-only container is reported as `GfxError::Io` with `PermissionDenied` for every path.
+    pub fn delete_files<'a, I>(&self, paths: I) -> Vec<(String, Result<(), GfxError>)>
+        where I: IntoIterator<Item = &'a str>
+    {
+        paths.into_iter()
+            .map(|path| (path.to_owned(), self.delete_file(path).map_err(GfxError::from)))
+            .collect()
+    }
+
+    /// Creates or truncates the file at `path` and writes `data` to it
+    pub fn write_file<P: AsRef<Path>>(&self, path: P, data: &[u8]) -> io::Result<()> {
+        self.create_file(path, 0)?.write_all(data)
+    }
+
+    /// Renames the file `from` to `to`, replacing `to` if it exists
+    ///
+    /// The dll has no native rename, so the content is copied to the new name before the old file
+    /// is deleted. Renaming a file to itself does nothing. If the names only differ in case, the
+    /// file is moved through a temporary name, since the dll may or may not treat both names as
+    /// the same file.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        let (from_path, to_path) = (self.absolute_path(&from)?, self.absolute_path(&to)?);
+        if from_path == to_path {
+            return Ok(());
+        }
+        if same_path(&from_path, &to_path) {
+            let tmp = self.unused_tmp_path(to.as_ref());
+            self.rename(from.as_ref(), &tmp)?;
+            return self.rename(&tmp, to.as_ref());
+        }
+        {
+            let mut src = self.open_file(&from, Access::OpenExisting, 0)?;
+            let mut dst = self.create_file(to, 0)?;
+            io::copy(&mut src, &mut dst)?;
+        }
+        self.delete_file(from)
+    }
+
+    /// Replaces the content of the file at `path` with `data` by writing to a temporary sibling
+    /// first, which is then moved into place
+    ///
+    /// The temporary file gets a name that doesn't exist yet, so no other file is touched. If
+    /// writing it fails, it is removed again and the original stays untouched. Atomicity is
+    /// best-effort only: since the dll can't rename files, moving the temporary file into place is
+    /// itself a copy. Should that last step fail, the temporary file is kept as it then holds the
+    /// only complete copy of the data.
+    pub fn write_file_atomic<P: AsRef<Path>>(&self, path: P, data: &[u8]) -> io::Result<()> {
+        let path = path.as_ref();
+        let tmp = self.unused_tmp_path(path);
+        if let Err(e) = self.write_file(&tmp, data) {
+            let _ = self.delete_file(&tmp);
+            return Err(e);
+        }
+        self.rename(&tmp, path)
+    }
+
+    /// Returns a sibling of `path` named `<path>.<n>.tmp` that doesn't exist yet
+    fn unused_tmp_path(&self, path: &Path) -> OsString {
+        (0..)
+            .map(|i| {
+                let mut tmp = path.as_os_str().to_owned();
+                tmp.push(format!(".{}.tmp", i));
+                tmp
+            })
+            .find(|tmp| !self.exists(tmp))
+            .unwrap()
     }
 
     /// Closes file by handle, not public because our handle wrapper manages its lifetime itself
     pub(crate) fn close_file(&self, file: &File) -> i32 {
-        vtable_call!(self, close_file, file.handle())
+        vtable_call!(self, close_file, file.handle(); file.handle())
     }
 
     /// Reads a number of bytes from a file
     pub(crate) fn read(&self, file: &File, lp_buffer: &mut [u8], bytes_to_read: i32, bytes_read: *mut u32) -> i32 {
-        vtable_call!(self, read, file.handle(), lp_buffer.as_mut_ptr() as *mut i8, bytes_to_read, bytes_read)
+        vtable_call!(self, read, file.handle(), lp_buffer.as_mut_ptr() as *mut i8, bytes_to_read, bytes_read; file.handle(), bytes_to_read)
     }
 
-    /// Writes a number of bytes to file
+    /// Writes a number of bytes to file, fails without calling the dll if the container is
+    /// read-only
     pub(crate) fn write(&self, file: &File, lp_buffer: &[u8], bytes_to_write: i32, bytes_written: *mut u32) -> i32 {
-        vtable_call!(self, write, file.handle(), lp_buffer.as_ptr() as *const i8, bytes_to_write, bytes_written)
+        if self.is_read_only() {
+            return 0;
+        }
+        vtable_call!(self, write, file.handle(), lp_buffer.as_ptr() as *const i8, bytes_to_write, bytes_written; file.handle(), bytes_to_write)
     }
 
-    pub fn cmd_line_path<'a>(&self) -> &'a CStr {
+    /// Returns a copy of the command line path the dll stored, invalid utf8 gets replaced
+    ///
+    /// Returns an empty string if the dll hasn't stored one.
+    pub fn cmd_line_path(&self) -> String {
         let charptr: *const c_char = vtable_call!(self, cmd_line_path);
-        unsafe { CStr::from_ptr(charptr) }
+        if charptr.is_null() {
+            return String::new();
+        }
+        unsafe { CStr::from_ptr(charptr) }.to_string_lossy().into_owned()
     }
 
-    pub fn cmd_line_exe<'a>(&self) -> &'a CStr {
+    /// Returns a copy of the executable name the dll stored, invalid utf8 gets replaced
+    ///
+    /// Returns an empty string if the dll hasn't stored one.
+    pub fn cmd_line_exe(&self) -> String {
         let charptr: *const c_char = vtable_call!(self, cmd_line_exe);
-        unsafe { CStr::from_ptr(charptr) }
+        if charptr.is_null() {
+            return String::new();
+        }
+        unsafe { CStr::from_ptr(charptr) }.to_string_lossy().into_owned()
     }
 
-    pub fn get_unknown(&self, unknown: *mut UnknownPair) -> *mut UnknownPair {
-        vtable_call!(self, get_unknown, unknown)
+    /// Returns a copy of the pair last stored with `set_unknown`
+    pub fn get_unknown(&self) -> UnknownPair {
+        let mut unknown = UnknownPair::default();
+        vtable_call!(self, get_unknown, &mut unknown);
+        unknown
     }
 
+    /// Stores a pair of integers that can be read back with `get_unknown`
     pub fn set_unknown(&self, a: i32, b: i32) -> i32 {
-        vtable_call!(self, set_unknown, a, b)
+        vtable_call!(self, set_unknown, a, b; a, b)
     }
 
     /// Creates directory in the current pk2
-    pub fn create_directory(&self, name: &str) -> bool {
-        let name = cstring!(name);
-        vtable_call!(self, create_dir, name.as_ptr()) != 0
+    pub fn create_directory<P: AsRef<Path>>(&self, name: P) -> io::Result<()> {
+        self.check_writable()?;
+        let name = self.c_path(name)?;
+        match vtable_call!(self, create_dir, name.as_ptr(); name.to_string_lossy()) {
+            0 => Err(GfxError::Failed { op: "create_dir", code: 0 }.into()),
+            _ => {
+                self.update_current_directory();
+                Ok(())
+            },
+        }
     }
 
     /// Deletes directory in the current pk2
-    pub fn delete_directory(&self, name: &str) -> bool {
-        let name = cstring!(name);
-        vtable_call!(self, delete_dir, name.as_ptr()) != 0
+    pub fn delete_directory<P: AsRef<Path>>(&self, name: P) -> io::Result<()> {
+        self.check_writable()?;
+        let name = self.c_path(name)?;
+        match vtable_call!(self, delete_dir, name.as_ptr(); name.to_string_lossy()) {
+            0 => Err(GfxError::Failed { op: "delete_dir", code: 0 }.into()),
+            _ => {
+                self.update_current_directory();
+                Ok(())
+            },
+        }
     }
 
     /// Resets the current working directory in the current pk2
@@ -281,39 +924,145 @@ impl GFXFileManager {
     }
 
     /// Changes the current working directory
-    pub fn change_directory(&self, name: &str) -> bool {
-        let name = cstring!(name);
-        vtable_call!(self, change_dir, name.as_ptr()) != 0
+    pub fn change_directory<P: AsRef<Path>>(&self, name: P) -> bool {
+        let name = match self.c_path(name) {
+            Ok(name) => name,
+            Err(_) => return false,
+        };
+        vtable_call!(self, change_dir, name.as_ptr(); name.to_string_lossy()) != 0
     }
 
-    /// Returns the current directory's name or an utf8 error
-    pub fn get_directory_name(&self) -> Result<String, FromUtf8Error> {
-        let mut buf = Vec::with_capacity(255);
-        unsafe { buf.set_len(255) };
-        let ptr = buf.as_mut_ptr();
-        let len = vtable_call!(self, get_dir_name, 200, ptr as *mut i8);
-        buf.truncate(len as usize);
-        String::from_utf8(buf)
+    /// Returns the current directory's name
+    ///
+    /// Fails if the dll reports an error or the name isn't valid in the manager's encoding.
+    pub fn get_directory_name(&self) -> io::Result<String> {
+        read_string("get_dir_name", self.encoding(), |buf| {
+            vtable_call!(self, get_dir_name, buf.len(), buf.as_mut_ptr() as *mut i8; buf.len())
+        })
     }
 
-    pub fn set_virtual_path(&self, path: &str) -> bool {
-        let path = cstring!(path);
-        vtable_call!(self, set_virtual_path, path.as_ptr()) != 0
+    /// Sets the virtual path, fails for paths that don't fit into the dll's `MAX_PATH` sized
+    /// buffer
+    pub fn set_virtual_path<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = match self.c_path(path) {
+            Ok(path) => path,
+            Err(_) => return false,
+        };
+        if path.as_bytes().len() >= VIRTUAL_PATH_BUF_LEN {
+            return false;
+        }
+        vtable_call!(self, set_virtual_path, path.as_ptr(); path.to_string_lossy()) != 0
     }
 
-    pub fn get_virtual_path(&self) -> Result<String, FromUtf8Error> {
-        let mut buf = Vec::with_capacity(255);
-        unsafe { buf.set_len(255) };
+    /// Returns the virtual path, fails if it isn't valid in the manager's encoding
+    ///
+    /// The dll doesn't take a length for this call and can't truncate, so the buffer has to be
+    /// large enough for any virtual path. Virtual paths are windows paths limited to `MAX_PATH`,
+    /// which `set_virtual_path` enforces, so a single buffer of that size is used.
+    pub fn get_virtual_path(&self) -> io::Result<String> {
+        let mut buf = vec![0u8; VIRTUAL_PATH_BUF_LEN];
         vtable_call!(self, get_virtual_path, buf.as_mut_ptr() as *mut i8);
-        if let Some(null_pos) = buf.iter().position(|&x| x == 0) {
-            buf.truncate(null_pos);
-        }
-        String::from_utf8(buf)
+        let len = buf.iter().position(|&x| x == 0).unwrap_or_else(|| buf.len());
+        self.encoding().decode(&buf[..len]).map(|path| path.into_owned())
     }
 
     pub fn find_first_file(&self, search: &mut SearchResult, pattern: &str, entry: &mut ResultEntry) {
         let pattern = cstring!(pattern);
-        vtable_call!(self, find_first_file, search.inner_mut(), pattern.as_ptr(), entry);
+        vtable_call!(self, find_first_file, search.inner_mut(), pattern.as_ptr(), entry; pattern.to_string_lossy());
+    }
+
+    /// Starts a search in the current directory and returns an iterator over the matched entries
+    ///
+    /// See the `glob` module for the supported pattern syntax.
+    pub fn search<'a>(&'a self, pattern: &str) -> io::Result<SearchResult<'a>> {
+        let mut search = SearchResult::new(self);
+        let mut entry = ResultEntry::empty();
+        self.find_first_file(&mut search, pattern, &mut entry);
+        if !search.success() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no entry matched the pattern"));
+        }
+        search.set_first(entry);
+        Ok(search)
+    }
+
+    /// Returns the names of the directories inside the current directory, without the `.` and
+    /// `..` entries
+    pub fn list_directories(&self) -> io::Result<Vec<String>> {
+        let search = match self.search("*") {
+            Ok(search) => search,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(search
+            .filter(|entry| entry.is_directory())
+            .map(|entry| entry.name_in(self.encoding()).into_owned())
+            .filter(|name| name != "." && name != "..")
+            .collect())
+    }
+
+    /// Looks up `path` ignoring the case of every component and returns the path as it is stored
+    /// in the container, or `None` if nothing matches
+    ///
+    /// Whether the dll's own lookups are case-sensitive depends on how the names were stored, so
+    /// use this to correct user supplied paths before opening them. A path without any component,
+    /// like the root, names no entry and gives `None`.
+    pub fn find_ignore_case(&self, path: &str) -> io::Result<Option<String>> {
+        let components: Vec<&str> = path.split(|c| c == '\\' || c == '/').filter(|c| !c.is_empty()).collect();
+        if components.is_empty() {
+            return Ok(None);
+        }
+        let absolute = path.starts_with('\\') || path.starts_with('/');
+        let _guard = DirGuard::enter(self, if absolute { "\\" } else { "" })?;
+        let mut found = if absolute { String::from("\\") } else { String::new() };
+        for (i, component) in components.iter().enumerate() {
+            let wanted = component.to_lowercase();
+            let name = match self.search("*") {
+                Ok(search) => search
+                    .map(|entry| entry.name_in(self.encoding()).into_owned())
+                    .find(|name| name.to_lowercase() == wanted),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+            let name = match name {
+                Some(name) => name,
+                None => return Ok(None),
+            };
+            if i + 1 < components.len() && !self.change_directory(&name) {
+                return Ok(None);
+            }
+            if !found.is_empty() && !found.ends_with('\\') {
+                found.push('\\');
+            }
+            found.push_str(&name);
+        }
+        Ok(Some(found))
+    }
+
+    /// Like `search`, but takes a unix style glob that gets translated with `translate_glob`
+    pub fn search_glob<'a>(&'a self, glob: &str) -> io::Result<SearchResult<'a>> {
+        self.search(&translate_glob(glob)?)
+    }
+
+    /// Searches the current directory for entries whose name ends with `ext`, ignoring case
+    ///
+    /// The leading dot of the extension is optional. The filtering happens on the rust side since
+    /// the dll's pattern matching behaves inconsistently. An empty directory yields no entries.
+    pub fn search_ext<'a>(&'a self, ext: &str) -> io::Result<impl Iterator<Item = ResultEntry> + 'a> {
+        let ext = if ext.starts_with('.') {
+            ext.to_lowercase()
+        } else {
+            format!(".{}", ext.to_lowercase())
+        };
+        let search = match self.search("*") {
+            Ok(search) => Some(search),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let encoding = self.encoding();
+        Ok(search
+            .into_iter()
+            .flatten()
+            .filter(move |entry| entry.name_in(encoding).to_lowercase().ends_with(&ext)))
     }
 
     pub fn find_next_file(&self, search: &mut SearchResult, entry: &mut ResultEntry) -> i32 {
@@ -324,30 +1073,29 @@ impl GFXFileManager {
         vtable_call!(self, close_search_result, search)
     }
 
-    pub(crate) fn file_name_from_handle(&self, file: &File) -> Result<String, FromUtf8Error> {
-        let mut buf = Vec::with_capacity(512);
-        unsafe { buf.set_len(512) };
-        vtable_call!(self, file_name_from_handle, file.handle(), buf.as_mut_ptr() as *mut i8, buf.len());
-        if let Some(null_pos) = buf.iter().position(|&x| x == 0) {
-            buf.truncate(null_pos);
-        }
-        String::from_utf8(buf)
+    /// Returns the name of the file behind the handle, decoded with the manager's encoding
+    pub(crate) fn file_name_from_handle(&self, file: &File) -> io::Result<String> {
+        let name = read_bytes("file_name_from_handle", |buf| {
+            vtable_call!(self, file_name_from_handle, file.handle(), buf.as_mut_ptr() as *mut i8, buf.len(); file.handle(), buf.len());
+            buf.iter().position(|&x| x == 0).unwrap_or_else(|| buf.len()) as c_int
+        })?;
+        self.encoding().decode(&name).map(|name| name.into_owned())
     }
 
     pub(crate) fn get_file_size(&self, file: &File) -> i32 {
-        vtable_call!(self, get_file_size, file.handle(), null_mut())
+        vtable_call!(self, get_file_size, file.handle(), null_mut(); file.handle())
     }
 
     pub(crate) fn get_file_time(&self, file: &File, creation_time: LPFILETIME, last_write_time: LPFILETIME) -> bool {
-        vtable_call!(self, get_file_time, file.handle(), creation_time, last_write_time)
+        vtable_call!(self, get_file_time, file.handle(), creation_time, last_write_time; file.handle())
     }
 
     pub(crate) fn set_file_time(&self, file: &File, creation_time: LPFILETIME, last_write_time: LPFILETIME) -> bool {
-        vtable_call!(self, set_file_time, file.handle(), creation_time, last_write_time)
+        vtable_call!(self, set_file_time, file.handle(), creation_time, last_write_time; file.handle())
     }
 
     pub(crate) fn seek(&self, file: &File, distance_to_move: c_long, move_method: DWORD) -> i32{
-        vtable_call!(self, seek, file.handle(), distance_to_move, move_method)
+        vtable_call!(self, seek, file.handle(), distance_to_move, move_method; file.handle(), distance_to_move, move_method)
     }
 
     pub fn get_hwnd(&self) -> HWND {
@@ -355,44 +1103,80 @@ impl GFXFileManager {
     }
 
     pub fn set_hwnd(&self, hwnd: HWND) -> i32 {
-        vtable_call!(self, set_hwnd, hwnd)
+        vtable_call!(self, set_hwnd, hwnd; hwnd)
     }
 
     pub fn register_error_handler(&self, callback: ErrorHandler) -> i32 {
         vtable_call!(self, register_error_handler, callback)
     }
 
-    pub fn import_directory(&self, srcdir: &str, dstdir: &str, dir_name: &str, create_target_dir: bool) -> i32 {
-        let srcdir = cstring!(srcdir);
-        let dstdir = cstring!(dstdir);
-        let dir_name = cstring!(dir_name);
-        vtable_call!(self, import_dir, srcdir.as_ptr(), dstdir.as_ptr(), dir_name.as_ptr(), create_target_dir)
+    /// Imports the directory `dir_name` of `srcdir` on disk into `dstdir` in the container
+    ///
+    /// Fails with `PermissionDenied` without calling the dll if the container is read-only.
+    pub fn import_directory<P, Q, R>(&self, srcdir: P, dstdir: Q, dir_name: R, create_target_dir: bool) -> io::Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>
+    {
+        self.check_writable()?;
+        let srcdir = disk_c_path(srcdir)?;
+        let dstdir = self.c_path(dstdir)?;
+        let dir_name = self.c_path(dir_name)?;
+        vtable_call_os!(self, import_dir, srcdir.as_ptr(), dstdir.as_ptr(), dir_name.as_ptr(), create_target_dir; srcdir.to_string_lossy(), dstdir.to_string_lossy(), dir_name.to_string_lossy(), create_target_dir)
     }
 
-    pub fn import_file(&self, srcdir: &str, dstdir: &str, filename: &str, create_target_dir: bool) -> i32 {
-        let srcdir = cstring!(srcdir);
-        let dstdir = cstring!(dstdir);
-        let filename = cstring!(filename);
-        vtable_call!(self, import_file, srcdir.as_ptr(), dstdir.as_ptr(), filename.as_ptr(), create_target_dir)
+    /// Imports the file `filename` of `srcdir` on disk into `dstdir` in the container
+    ///
+    /// Fails with `PermissionDenied` without calling the dll if the container is read-only.
+    pub fn import_file<P, Q, R>(&self, srcdir: P, dstdir: Q, filename: R, create_target_dir: bool) -> io::Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>
+    {
+        self.check_writable()?;
+        let srcdir = disk_c_path(srcdir)?;
+        let dstdir = self.c_path(dstdir)?;
+        let filename = self.c_path(filename)?;
+        vtable_call_os!(self, import_file, srcdir.as_ptr(), dstdir.as_ptr(), filename.as_ptr(), create_target_dir; srcdir.to_string_lossy(), dstdir.to_string_lossy(), filename.to_string_lossy(), create_target_dir)
     }
 
-    pub fn export_directory(&self, srcdir: &str, dstdir: &str, dir_name: &str, create_target_dir: bool) -> i32 {
-        let srcdir = cstring!(srcdir);
-        let dstdir = cstring!(dstdir);
-        let dir_name = cstring!(dir_name);
-        vtable_call!(self, export_dir, srcdir.as_ptr(), dstdir.as_ptr(), dir_name.as_ptr(), create_target_dir)
+    /// Exports the directory `dir_name` of `srcdir` in the container into `dstdir` on disk
+    pub fn export_directory<P, Q, R>(&self, srcdir: P, dstdir: Q, dir_name: R, create_target_dir: bool) -> io::Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>
+    {
+        let dstdir = disk_c_path(dstdir)?;
+        let srcdir = self.c_path(srcdir)?;
+        let dir_name = self.c_path(dir_name)?;
+        vtable_call_os!(self, export_dir, srcdir.as_ptr(), dstdir.as_ptr(), dir_name.as_ptr(), create_target_dir; srcdir.to_string_lossy(), dstdir.to_string_lossy(), dir_name.to_string_lossy(), create_target_dir)
     }
 
-    pub fn export_file(&self, srcdir: &str, dstdir: &str, filename: &str, create_target_dir: bool) -> i32 {
-        let srcdir = cstring!(srcdir);
-        let dstdir = cstring!(dstdir);
-        let filename = cstring!(filename);
-        vtable_call!(self, export_file, srcdir.as_ptr(), dstdir.as_ptr(), filename.as_ptr(), create_target_dir)
+    /// Exports the file `filename` of `srcdir` in the container into `dstdir` on disk
+    pub fn export_file<P, Q, R>(&self, srcdir: P, dstdir: Q, filename: R, create_target_dir: bool) -> io::Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>
+    {
+        let dstdir = disk_c_path(dstdir)?;
+        let srcdir = self.c_path(srcdir)?;
+        let filename = self.c_path(filename)?;
+        vtable_call_os!(self, export_file, srcdir.as_ptr(), dstdir.as_ptr(), filename.as_ptr(), create_target_dir; srcdir.to_string_lossy(), dstdir.to_string_lossy(), filename.to_string_lossy(), create_target_dir)
+    }
+
+    pub fn file_exists<P: AsRef<Path>>(&self, name: P, flags: i32) -> i32 {
+        let name = match self.c_path(name) {
+            Ok(name) => name,
+            Err(_) => return 0,
+        };
+        vtable_call!(self, file_exists, name.as_ptr(), flags; name.to_string_lossy(), flags)
+    }
+
+    /// Returns true if a file or directory exists at `path`
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.file_exists(path, 0) != 0
     }
 
-    pub fn file_exists(&self, name: &str, flags: i32) -> i32 {
-        let name = cstring!(name);
-        vtable_call!(self, file_exists, name.as_ptr(), flags)
+    /// Checks every path in `paths` with `exists` and returns the outcome for each path in input
+    /// order
+    pub fn exists_many<'a, I>(&self, paths: I) -> Vec<(String, bool)>
+        where I: IntoIterator<Item = &'a str>
+    {
+        paths.into_iter()
+            .map(|path| (path.to_owned(), self.exists(path)))
+            .collect()
     }
 
     pub fn show_dialog(&self, data: &mut DialogData) -> i32 {
@@ -401,15 +1185,100 @@ impl GFXFileManager {
 
     pub fn for_each_entry_in_container(&self, callback: ForEachCallback, filter: &str, userstate: *mut c_void) -> i32 {
         let filter = cstring!(filter);
-        vtable_call!(self, for_each_entry_in_container, callback, filter.as_ptr(), userstate)
+        vtable_call!(self, for_each_entry_in_container, callback, filter.as_ptr(), userstate; filter.to_string_lossy())
+    }
+
+    /// Traverses all entries matching `filter`, descending into directories, and calls `callback`
+    /// for every entry with the traversal state and the entry's path relative to the current
+    /// directory
+    ///
+    /// On `LeaveDir` the path is still the one of the directory that is left. If `callback`
+    /// panics, the remaining entries are skipped and the panic continues once the dll returned.
+    pub fn for_each<F>(&self, filter: &str, callback: F) -> io::Result<()>
+        where F: FnMut(CallbackState, &ResultEntry, &str)
+    {
+        let mut state = ForEachState {
+            visitor: EntryVisitor { callback, encoding: self.encoding(), path: String::new(), dirs: Vec::new() },
+            panic: PanicSlot::default(),
+        };
+        let res = {
+            let userstate = &mut state as *mut ForEachState<F> as *mut c_void;
+            self.for_each_entry_in_container(for_each_trampoline::<F>, filter, userstate)
+        };
+        state.panic.resume();
+        match res {
+            0 => Err(io::Error::new(io::ErrorKind::Other, "for_each_entry_in_container failed")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Traverses every entry of the container, see `for_each`
+    pub fn walk<F>(&self, callback: F) -> io::Result<()>
+        where F: FnMut(CallbackState, &ResultEntry, &str)
+    {
+        self.for_each("*", callback)
+    }
+
+    /// Collects all entries below the directory `root` with their paths relative to `root`
+    pub(crate) fn entries_under(&self, root: &str) -> io::Result<Vec<WalkEntry>> {
+        let _guard = DirGuard::enter(self, root)?;
+        let mut entries = Vec::new();
+        self.walk(|state, entry, path| {
+            match state {
+                CallbackState::EnterDir => {
+                    entries.push(WalkEntry { path: path.to_owned(), is_dir: true, size: 0 });
+                },
+                CallbackState::File => {
+                    entries.push(WalkEntry { path: path.to_owned(), is_dir: false, size: entry.size() as u64 });
+                },
+                _ => (),
+            }
+        })?;
+        Ok(entries)
     }
 
+    /// Makes the dll reload its cached entries of the current directory
+    ///
+    /// The dll keeps the entries of the current directory cached, so searches may not reflect
+    /// changes to it until this is called. The wrappers creating or deleting files and
+    /// directories call it automatically.
     pub fn update_current_directory(&self) -> i32 {
         vtable_call!(self, update_current_dir)
     }
 
+    /// Refreshes the cached entries of the current directory, see `update_current_directory`
+    ///
+    /// Fails if the dll returns a negative value. What a non-negative value means is unknown, so
+    /// it isn't reported.
+    pub fn refresh_directory(&self) -> io::Result<()> {
+        match self.update_current_directory() {
+            res if res < 0 => Err(GfxError::Failed { op: "update_current_directory", code: res }.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Makes sure all changes made so far are handed to the dll
+    ///
+    /// The dll has no flush function, a file's data is written to the container when its handle
+    /// gets closed. This therefore fails while files of this manager are still open, and otherwise
+    /// asks the dll to update the current directory so its cached directory entries are written
+    /// as well. Durability beyond that, like flushing the os' file cache, is up to the dll.
+    pub fn commit(&self) -> io::Result<()> {
+        if !self.is_open() {
+            return Err(io::Error::new(io::ErrorKind::Other, "no container is open"));
+        }
+        let open_files = self.open_file_count();
+        if open_files > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} files are still open and may hold unwritten data", open_files),
+            ));
+        }
+        self.refresh_directory()
+    }
+
     pub fn function_50(&self, i1: i32) -> i32 {
-        vtable_call!(self, function_50, i1)
+        vtable_call!(self, function_50, i1; i1)
     }
 
     pub fn get_version(&self) -> i32 {
@@ -417,7 +1286,7 @@ impl GFXFileManager {
     }
 
     pub fn check_version(&self, version: i32) -> i32 {
-        vtable_call!(self, check_version, version)
+        vtable_call!(self, check_version, version; version)
     }
 
     pub fn unlock(&self) -> bool {
@@ -425,10 +1294,154 @@ impl GFXFileManager {
     }
 
     pub fn lock(&self, i1: i32) -> bool {
-        vtable_call!(self, lock, i1) != 0
+        vtable_call!(self, lock, i1; i1) != 0
     }
 }
 
+/// Resets the thread's last os error, so a failure the dll doesn't set an error for isn't blamed on
+/// an earlier one
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
+fn clear_last_error() {
+    unsafe { SetLastError(0) }
+}
+
+/// Only the mock exists on other targets, which never sets an os error
+#[cfg(not(all(target_os = "windows", target_arch = "x86")))]
+fn clear_last_error() {}
+
+/// Turns the result of a dll function returning zero on failure into an `io::Result`, using the
+/// os error the dll set or `GfxError::Failed` if it set none
+fn os_result(op: &'static str, res: c_int) -> io::Result<()> {
+    if res != 0 {
+        return Ok(());
+    }
+    let e = io::Error::last_os_error();
+    if cfg!(all(target_os = "windows", target_arch = "x86")) && e.raw_os_error() != Some(0) {
+        return Err(e);
+    }
+    Err(GfxError::Failed { op, code: res }.into())
+}
+
+/// Converts a path on disk into the form the dll expects, the system's ansi code page
+///
+/// The path is passed on as it is, without the normalization applied to container paths, so UNC
+/// paths keep their leading `\\`. Fails with `InvalidInput` if the path can't be represented.
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
+fn disk_c_path<P: AsRef<Path>>(path: P) -> io::Result<CString> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::null;
+
+    const CP_UTF8: u32 = 65001;
+    const WC_NO_BEST_FIT_CHARS: DWORD = 0x400;
+
+    let path = path.as_ref();
+    let unrepresentable = || io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} can't be represented in the ansi code page", path.display()),
+    );
+    let bytes = if unsafe { GetACP() } == CP_UTF8 {
+        path.to_str().ok_or_else(unrepresentable)?.as_bytes().to_vec()
+    } else {
+        let wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+        if wide.is_empty() {
+            Vec::new()
+        } else {
+            let mut used_default_char = 0;
+            let len = unsafe {
+                WideCharToMultiByte(0, WC_NO_BEST_FIT_CHARS, wide.as_ptr(), wide.len() as c_int, null_mut(), 0, null(), null_mut())
+            };
+            let mut bytes = vec![0u8; len.max(0) as usize];
+            let written = unsafe {
+                WideCharToMultiByte(
+                    0, WC_NO_BEST_FIT_CHARS, wide.as_ptr(), wide.len() as c_int,
+                    bytes.as_mut_ptr() as *mut c_char, len, null(), &mut used_default_char,
+                )
+            };
+            if written <= 0 || used_default_char != 0 {
+                return Err(unrepresentable());
+            }
+            bytes
+        }
+    };
+    CString::new(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Converts a path on disk into the form the dll expects, only the mock exists on other targets
+/// and it doesn't touch the disk, so the path is passed on as utf8
+#[cfg(not(all(target_os = "windows", target_arch = "x86")))]
+fn disk_c_path<P: AsRef<Path>>(path: P) -> io::Result<CString> {
+    let path = path.as_ref();
+    let path = path.to_str().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not valid unicode", path.display()))
+    })?;
+    CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Whether two paths returned by `absolute_path` may name the same file, assuming the dll might
+/// ignore case
+pub(crate) fn same_path(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// An entry found by `GFXFileManager::entries_under`
+pub(crate) struct WalkEntry {
+    pub(crate) path: String,
+    pub(crate) is_dir: bool,
+    pub(crate) size: u64,
+}
+
+/// Changes the current directory of a manager and changes it back when dropped
+pub(crate) struct DirGuard<'a> {
+    file_manager: &'a GFXFileManager,
+    previous: String,
+}
+
+impl<'a> DirGuard<'a> {
+    pub(crate) fn enter(file_manager: &'a GFXFileManager, dir: &str) -> io::Result<Self> {
+        let previous = file_manager.get_directory_name()?;
+        if !dir.is_empty() && !file_manager.change_directory(dir) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no directory named {}", dir)));
+        }
+        Ok(DirGuard { file_manager, previous })
+    }
+}
+
+impl<'a> Drop for DirGuard<'a> {
+    fn drop(&mut self) {
+        self.file_manager.change_directory(&self.previous);
+    }
+}
+
+/// Calls `fill` with a zeroed buffer that it passes to the dll and which returns the length of the
+/// written string. If the string together with its terminator doesn't fit into the buffer it was
+/// most likely truncated, so the buffer gets doubled and the call retried. A negative length is
+/// an error code of the dll call `op`.
+fn read_bytes<F>(op: &'static str, mut fill: F) -> io::Result<Vec<u8>>
+    where F: FnMut(&mut [u8]) -> c_int
+{
+    let mut len = PATH_BUF_LEN;
+    loop {
+        let mut buf = vec![0u8; len];
+        let written = fill(&mut buf);
+        if written < 0 {
+            return Err(GfxError::Failed { op, code: written }.into());
+        }
+        let written = written as usize;
+        if written + 1 < len || len >= MAX_PATH_BUF_LEN {
+            buf.truncate(written.min(len));
+            return Ok(buf);
+        }
+        len *= 2;
+    }
+}
+
+/// Like `read_bytes`, but decodes the bytes with `encoding`
+fn read_string<F>(op: &'static str, encoding: Encoding, fill: F) -> io::Result<String>
+    where F: FnMut(&mut [u8]) -> c_int
+{
+    encoding.decode(&read_bytes(op, fill)?).map(|s| s.into_owned())
+}
+
 impl Drop for GFXFileManager {
     fn drop(&mut self) {
         self.close_all_files();
@@ -439,69 +1452,70 @@ impl Drop for GFXFileManager {
 }
 
 #[repr(C)]
-struct VTable {
-    mode: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    config_set: extern "thiscall" fn(*mut IFileManager, c_int, c_int) -> c_int,
-    config_get: extern "thiscall" fn(*mut IFileManager, c_int, c_int) -> c_int,
-    create_container: extern "thiscall" fn(*mut IFileManager, *const c_char, *const c_char) -> c_int,
-    open_container: extern "thiscall" fn(*mut IFileManager, *const c_char, *const c_char, c_int) -> c_int,
-    close_container: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    is_open: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    close_all_files: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    main_module_handle: extern "thiscall" fn(*mut IFileManager) -> HMODULE,
-    function_9: extern "thiscall" fn(*mut IFileManager, c_int) -> c_int,
-    open_file_cj: extern "thiscall" fn(*mut IFileManager, *mut CJArchiveFm, *const c_char, c_int, c_int) -> c_int,
-    open_file: extern "thiscall" fn(*mut IFileManager, *const c_char, c_int, c_int) -> c_int,
-    function_12: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    function_13: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    create_file_cj: extern "thiscall" fn(*mut IFileManager, *mut CJArchiveFm, *const c_char, c_int) -> c_int,
-    create_file: extern "thiscall" fn(*mut IFileManager, *const c_char, c_int) -> c_int,
-    delete_file: extern "thiscall" fn(*mut IFileManager, *const c_char,) -> c_int,
-    close_file: extern "thiscall" fn(*mut IFileManager, c_int) -> c_int,
-    read: extern "thiscall" fn(*mut IFileManager, c_int, *mut c_char, c_int, *mut c_ulong) -> c_int,
-    write: extern "thiscall" fn(*mut IFileManager, c_int, *const c_char, c_int, *mut c_ulong) -> c_int,
-    cmd_line_path: extern "thiscall" fn(*mut IFileManager) -> *mut c_char,
-    cmd_line_exe: extern "thiscall" fn(*mut IFileManager) -> *mut c_char,
-    get_unknown: extern "thiscall" fn(*mut IFileManager, *mut UnknownPair) -> *mut UnknownPair,
-    set_unknown: extern "thiscall" fn(*mut IFileManager, c_int, c_int) -> c_int,
-    create_dir: extern "thiscall" fn(*mut IFileManager, *const c_char) -> c_int,
-    delete_dir: extern "thiscall" fn(*mut IFileManager, *const c_char) -> c_int,
-    reset_dir: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    change_dir: extern "thiscall" fn(*mut IFileManager, *const c_char) -> c_int,
-    get_dir_name: extern "thiscall" fn(*mut IFileManager, usize, *mut c_char) -> c_int,
-    set_virtual_path: extern "thiscall" fn(*mut IFileManager, *const c_char) -> c_int,
-    get_virtual_path: extern "thiscall" fn(*mut IFileManager, *mut c_char) -> c_int,
-    find_first_file: extern "thiscall" fn(*mut IFileManager, *mut GFXSearchResult, *const c_char, *mut ResultEntry) -> *mut GFXSearchResult,
-    find_next_file: extern "thiscall" fn(*mut IFileManager, *mut GFXSearchResult, *mut ResultEntry) -> c_int,
-    close_search_result: extern "thiscall" fn(*mut IFileManager, *mut GFXSearchResult) -> c_int,
-    file_name_from_handle: extern "thiscall" fn(*mut IFileManager, c_int, *mut c_char, usize) -> c_int,
-    get_file_size: extern "thiscall" fn(*mut IFileManager, c_int, LPDWORD) -> c_int,
-    get_file_time: extern "thiscall" fn(*mut IFileManager, c_int, LPFILETIME, LPFILETIME) -> bool,
-    set_file_time: extern "thiscall" fn(*mut IFileManager, c_int, LPFILETIME, LPFILETIME) -> bool,
-    seek: extern "thiscall" fn(*mut IFileManager, c_int, c_long, DWORD) -> c_int,
-    get_hwnd: extern "thiscall" fn(*mut IFileManager) -> HWND,
-    set_hwnd: extern "thiscall" fn(*mut IFileManager, HWND) -> c_int,
-    register_error_handler: extern "thiscall" fn(*mut IFileManager, ErrorHandler) -> c_int,
-    import_dir: extern "thiscall" fn(*mut IFileManager, *const c_char, *const c_char, *const c_char, bool) -> c_int,
-    import_file: extern "thiscall" fn(*mut IFileManager, *const c_char, *const c_char, *const c_char, bool) -> c_int,
-    export_dir: extern "thiscall" fn(*mut IFileManager, *const c_char, *const c_char, *const c_char, bool) -> c_int,
-    export_file: extern "thiscall" fn(*mut IFileManager, *const c_char, *const c_char, *const c_char, bool) -> c_int,
-    file_exists: extern "thiscall" fn(*mut IFileManager, *const c_char, c_int) -> c_int,
-    show_dialog: extern "thiscall" fn(*mut IFileManager, *mut DialogData) -> c_int,
-    for_each_entry_in_container: extern "thiscall" fn(*mut IFileManager, ForEachCallback, *const c_char, *mut c_void) -> c_int,
-    update_current_dir: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    function_50: extern "thiscall" fn(*mut IFileManager, c_int) -> c_int,
-    get_version: extern "thiscall" fn(*mut IFileManager) -> c_int,
-    check_version: extern "thiscall" fn(*mut IFileManager, c_int) -> c_int,
-    lock: extern "thiscall" fn(*mut IFileManager, c_int) -> c_int,
-    unlock: extern "thiscall" fn(*mut IFileManager) -> c_int
+pub(crate) struct VTable {
+    pub(crate) mode: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) config_set: thiscall!(fn(*mut IFileManager, c_int, c_int) -> c_int),
+    pub(crate) config_get: thiscall!(fn(*mut IFileManager, c_int, c_int) -> c_int),
+    pub(crate) create_container: thiscall!(fn(*mut IFileManager, *const c_char, *const c_char) -> c_int),
+    pub(crate) open_container: thiscall!(fn(*mut IFileManager, *const c_char, *const c_char, c_int) -> c_int),
+    pub(crate) close_container: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) is_open: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) close_all_files: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) main_module_handle: thiscall!(fn(*mut IFileManager) -> HMODULE),
+    pub(crate) function_9: thiscall!(fn(*mut IFileManager, c_int) -> c_int),
+    pub(crate) open_file_cj: thiscall!(fn(*mut IFileManager, *mut CJArchiveFm, *const c_char, c_int, c_int) -> c_int),
+    pub(crate) open_file: thiscall!(fn(*mut IFileManager, *const c_char, c_int, c_int) -> c_int),
+    pub(crate) function_12: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) function_13: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) create_file_cj: thiscall!(fn(*mut IFileManager, *mut CJArchiveFm, *const c_char, c_int) -> c_int),
+    pub(crate) create_file: thiscall!(fn(*mut IFileManager, *const c_char, c_int) -> c_int),
+    pub(crate) delete_file: thiscall!(fn(*mut IFileManager, *const c_char,) -> c_int),
+    pub(crate) close_file: thiscall!(fn(*mut IFileManager, c_int) -> c_int),
+    pub(crate) read: thiscall!(fn(*mut IFileManager, c_int, *mut c_char, c_int, *mut c_ulong) -> c_int),
+    pub(crate) write: thiscall!(fn(*mut IFileManager, c_int, *const c_char, c_int, *mut c_ulong) -> c_int),
+    pub(crate) cmd_line_path: thiscall!(fn(*mut IFileManager) -> *mut c_char),
+    pub(crate) cmd_line_exe: thiscall!(fn(*mut IFileManager) -> *mut c_char),
+    pub(crate) get_unknown: thiscall!(fn(*mut IFileManager, *mut UnknownPair) -> *mut UnknownPair),
+    pub(crate) set_unknown: thiscall!(fn(*mut IFileManager, c_int, c_int) -> c_int),
+    pub(crate) create_dir: thiscall!(fn(*mut IFileManager, *const c_char) -> c_int),
+    pub(crate) delete_dir: thiscall!(fn(*mut IFileManager, *const c_char) -> c_int),
+    pub(crate) reset_dir: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) change_dir: thiscall!(fn(*mut IFileManager, *const c_char) -> c_int),
+    pub(crate) get_dir_name: thiscall!(fn(*mut IFileManager, usize, *mut c_char) -> c_int),
+    pub(crate) set_virtual_path: thiscall!(fn(*mut IFileManager, *const c_char) -> c_int),
+    pub(crate) get_virtual_path: thiscall!(fn(*mut IFileManager, *mut c_char) -> c_int),
+    pub(crate) find_first_file: thiscall!(fn(*mut IFileManager, *mut GFXSearchResult, *const c_char, *mut ResultEntry) -> *mut GFXSearchResult),
+    pub(crate) find_next_file: thiscall!(fn(*mut IFileManager, *mut GFXSearchResult, *mut ResultEntry) -> c_int),
+    pub(crate) close_search_result: thiscall!(fn(*mut IFileManager, *mut GFXSearchResult) -> c_int),
+    pub(crate) file_name_from_handle: thiscall!(fn(*mut IFileManager, c_int, *mut c_char, usize) -> c_int),
+    pub(crate) get_file_size: thiscall!(fn(*mut IFileManager, c_int, LPDWORD) -> c_int),
+    pub(crate) get_file_time: thiscall!(fn(*mut IFileManager, c_int, LPFILETIME, LPFILETIME) -> bool),
+    pub(crate) set_file_time: thiscall!(fn(*mut IFileManager, c_int, LPFILETIME, LPFILETIME) -> bool),
+    pub(crate) seek: thiscall!(fn(*mut IFileManager, c_int, c_long, DWORD) -> c_int),
+    pub(crate) get_hwnd: thiscall!(fn(*mut IFileManager) -> HWND),
+    pub(crate) set_hwnd: thiscall!(fn(*mut IFileManager, HWND) -> c_int),
+    pub(crate) register_error_handler: thiscall!(fn(*mut IFileManager, ErrorHandler) -> c_int),
+    pub(crate) import_dir: thiscall!(fn(*mut IFileManager, *const c_char, *const c_char, *const c_char, bool) -> c_int),
+    pub(crate) import_file: thiscall!(fn(*mut IFileManager, *const c_char, *const c_char, *const c_char, bool) -> c_int),
+    pub(crate) export_dir: thiscall!(fn(*mut IFileManager, *const c_char, *const c_char, *const c_char, bool) -> c_int),
+    pub(crate) export_file: thiscall!(fn(*mut IFileManager, *const c_char, *const c_char, *const c_char, bool) -> c_int),
+    pub(crate) file_exists: thiscall!(fn(*mut IFileManager, *const c_char, c_int) -> c_int),
+    pub(crate) show_dialog: thiscall!(fn(*mut IFileManager, *mut DialogData) -> c_int),
+    pub(crate) for_each_entry_in_container: thiscall!(fn(*mut IFileManager, ForEachCallback, *const c_char, *mut c_void) -> c_int),
+    pub(crate) update_current_dir: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) function_50: thiscall!(fn(*mut IFileManager, c_int) -> c_int),
+    pub(crate) get_version: thiscall!(fn(*mut IFileManager) -> c_int),
+    pub(crate) check_version: thiscall!(fn(*mut IFileManager, c_int) -> c_int),
+    pub(crate) lock: thiscall!(fn(*mut IFileManager, c_int) -> c_int),
+    pub(crate) unlock: thiscall!(fn(*mut IFileManager) -> c_int)
 }
 
 #[repr(C)]
 pub(crate) struct IFileManager {
-    vtable: *const VTable,
+    pub(crate) vtable: *const VTable,
 }
 
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
 impl IFileManager {
     fn new_ptr(mode: c_int, version: c_int) -> *mut IFileManager {
         let mut obj = null_mut();
@@ -510,8 +1524,109 @@ impl IFileManager {
     }
 }
 
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
 impl Drop for IFileManager {
     fn drop(&mut self) {
         unsafe { GFXDllReleaseObject(self) };
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::cell::Cell;
+    use std::io;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::{Access, CallbackState, GFXFileManager, Mode};
+    use encoding::Encoding;
+
+    #[test]
+    fn panicking_walk_callback_resumes_after_the_dll_returned() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        for name in &["a", "b", "c"] {
+            file_manager.write_file(name, b"data").unwrap();
+        }
+        let calls = Cell::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            file_manager.walk(|_, _, _| {
+                calls.set(calls.get() + 1);
+                panic!("callback panicked");
+            })
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"callback panicked"));
+        assert_eq!(calls.get(), 1);
+        // the manager is still usable after the dll returned normally
+        assert!(file_manager.walk(|_, _, _| ()).is_ok());
+    }
+
+    #[test]
+    fn names_are_decoded_with_the_managers_encoding() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        file_manager.set_encoding(Encoding::EucKr);
+        assert!(file_manager.create_container("", ""));
+        file_manager.create_directory("데이터").unwrap();
+        file_manager.write_file("데이터\\파일", b"data").unwrap();
+        assert_eq!(file_manager.list_directories().unwrap(), ["데이터"]);
+        let mut files = Vec::new();
+        file_manager.walk(|state, _, path| if state == CallbackState::File {
+            files.push(path.to_owned());
+        }).unwrap();
+        assert_eq!(files, ["데이터\\파일"]);
+        assert_eq!(file_manager.find_ignore_case("데이터\\파일").unwrap().unwrap(), "데이터\\파일");
+        assert!(file_manager.change_directory("데이터"));
+        assert_eq!(file_manager.get_directory_name().unwrap(), "\\데이터");
+    }
+
+    #[test]
+    fn read_only_containers_refuse_every_kind_of_write() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager.write_file("file", b"data").unwrap();
+        file_manager.open_container_readonly("", "").unwrap();
+        let denied = |res: io::Result<()>| res.unwrap_err().kind() == io::ErrorKind::PermissionDenied;
+        assert!(denied(file_manager.open_file("file", Access::CreateAlways, 0).map(|_| ())));
+        assert!(denied(file_manager.create_file("new", 0).map(|_| ())));
+        assert!(denied(file_manager.write_file("file", b"changed")));
+        assert!(denied(file_manager.delete_file("file")));
+        assert!(denied(file_manager.create_directory("dir")));
+        assert_eq!(file_manager.read_file("file").unwrap(), b"data");
+    }
+
+    #[test]
+    fn rename_changes_the_case_of_a_name() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager.write_file("a", b"data").unwrap();
+        file_manager.rename("a", "A").unwrap();
+        assert!(!file_manager.exists("a"));
+        assert_eq!(file_manager.read_file("A").unwrap(), b"data");
+        file_manager.rename("A", "\\.\\A").unwrap();
+        assert_eq!(file_manager.read_file("A").unwrap(), b"data");
+        assert_eq!(file_manager.search("*").unwrap().count(), 1);
+    }
+
+    #[test]
+    fn search_ext_in_an_empty_directory_yields_nothing() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        assert_eq!(file_manager.search_ext("txt").unwrap().count(), 0);
+        file_manager.write_file("a.TXT", b"data").unwrap();
+        file_manager.write_file("b.dat", b"data").unwrap();
+        let names: Vec<_> = file_manager.search_ext(".txt").unwrap().map(|e| e.name().into_owned()).collect();
+        assert_eq!(names, ["a.TXT"]);
+        assert_eq!(file_manager.search_ext("ddj").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn find_ignore_case_needs_a_component() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager.write_file("File", b"data").unwrap();
+        for path in &["", "\\", "/", "\\\\"] {
+            assert_eq!(file_manager.find_ignore_case(path).unwrap(), None);
+        }
+        assert_eq!(file_manager.find_ignore_case("\\file").unwrap().unwrap(), "\\File");
+    }
+}
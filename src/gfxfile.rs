@@ -1,30 +1,86 @@
-use std::io::{Initializer, Read, Result, Seek, SeekFrom, Write};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
 
-use winapi::c_int;
-use winapi::{FILETIME, LPFILETIME};
+use sys::c_int;
+use sys::{FILETIME, LPFILETIME};
 
-use file_manager::GFXFileManager;
+use error::GfxError;
+use file_manager::{Access, GFXFileManager, INVALID_HANDLE};
 
 pub struct File<'a> {
     handle: c_int,
     file_manager: &'a GFXFileManager,
+    /// The container generation the handle belongs to
+    generation: u32,
 }
 
 impl<'a> File<'a> {
-    pub fn new(file_manager: &'a GFXFileManager, handle: c_int) -> File {
+    pub(crate) fn new(file_manager: &'a GFXFileManager, handle: c_int) -> File {
+        file_manager.file_opened();
         File {
             handle,
             file_manager,
+            generation: file_manager.generation(),
         }
     }
 
+    /// Wraps a raw handle obtained from the dll, the returned File takes ownership of the handle
+    /// and closes it when dropped
+    ///
+    /// # Safety
+    ///
+    /// `handle` has to be an open handle belonging to `file_manager` that isn't owned by any
+    /// other File, otherwise it gets closed twice.
+    pub unsafe fn from_raw_handle(file_manager: &'a GFXFileManager, handle: i32) -> File {
+        File::new(file_manager, handle)
+    }
+
     #[inline(always)]
     pub(crate) fn handle(&self) -> c_int {
         self.handle
     }
 
+    /// Returns the raw handle without giving up ownership, the handle stays valid only as long as
+    /// this File is alive
+    pub fn as_raw_handle(&self) -> i32 {
+        self.handle
+    }
+
+    /// Whether the handle isn't the dll's invalid handle `INVALID_HANDLE`
+    ///
+    /// Files returned by the `open_file*` and `create_file*` methods are always valid, an invalid
+    /// one can only be created through `from_raw_handle`.
+    pub fn is_valid(&self) -> bool {
+        self.handle != INVALID_HANDLE
+    }
+
+    /// Whether the container this handle belongs to has been closed or replaced since it was
+    /// opened
+    pub(crate) fn is_stale(&self) -> bool {
+        self.generation != self.file_manager.generation()
+    }
+
+    /// Consumes the File and returns its raw handle without closing it
+    ///
+    /// The caller becomes responsible for the handle, it can be closed again by turning it back
+    /// into a File with `from_raw_handle`.
+    pub fn into_raw_handle(self) -> i32 {
+        let handle = self.handle;
+        self.file_manager.file_closed();
+        ::std::mem::forget(self);
+        handle
+    }
+
+    /// Returns the size of the file, or 0 if the dll can't determine it
     pub fn len(&self) -> u64 {
-        self.file_manager.get_file_size(self) as u64
+        self.try_len().unwrap_or(0)
+    }
+
+    /// Returns the size of the file, failing if the dll reports an error for the handle
+    pub fn try_len(&self) -> Result<u64> {
+        match self.file_manager.get_file_size(self) {
+            size if size < 0 => Err(GfxError::Failed { op: "get_file_size", code: size }.into()),
+            size => Ok(size as u64),
+        }
     }
 
     pub fn is_empty(&self) -> bool {
@@ -32,40 +88,178 @@ impl<'a> File<'a> {
     }
 
     /// Returns the creation and last write time of this file
-    pub fn file_time(&self) -> (FILETIME, FILETIME) {
-        unsafe {
-            let mut creation_time = ::std::mem::uninitialized();
-            let mut last_write_time = ::std::mem::uninitialized();
-            self.file_manager.get_file_time(self, &mut creation_time, &mut last_write_time);
-            (creation_time, last_write_time)
+    pub fn file_time(&self) -> Result<(FILETIME, FILETIME)> {
+        let mut creation_time = unsafe { ::std::mem::zeroed() };
+        let mut last_write_time = unsafe { ::std::mem::zeroed() };
+        if self.file_manager.get_file_time(self, &mut creation_time, &mut last_write_time) {
+            Ok((creation_time, last_write_time))
+        } else {
+            Err(GfxError::Failed { op: "get_file_time", code: 0 }.into())
         }
     }
 
-    pub fn set_file_time(&self, creation_time: LPFILETIME, last_write_time: LPFILETIME) {
-        self.file_manager.set_file_time(self, creation_time, last_write_time);
+    /// Sets the creation and last write time of this file
+    ///
+    /// Fails with `PermissionDenied` without calling the dll if the container is read-only.
+    pub fn set_file_time(&self, creation_time: LPFILETIME, last_write_time: LPFILETIME) -> Result<()> {
+        self.file_manager.check_writable()?;
+        if self.file_manager.set_file_time(self, creation_time, last_write_time) {
+            Ok(())
+        } else {
+            Err(GfxError::Failed { op: "set_file_time", code: 0 }.into())
+        }
     }
 
-    pub fn name(&self) -> String {
-        self.file_manager.file_name_from_handle(self).unwrap()
+    /// Moves the cursor back to the start of the file
+    pub fn rewind(&self) -> Result<()> {
+        self.seek_raw(0, 0, Some(0)).map(|_| ())
     }
-}
 
-impl<'a> Read for File<'a> {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let len = buf.len();
+    /// Moves the cursor to the end of the file and returns the new position, which is the length
+    /// of the file as long as it is below 4 GiB
+    pub fn seek_to_end(&self) -> Result<u64> {
+        self.seek_raw(0, 2, None)
+    }
+
+    /// Seeks to `pos`, also for offsets that don't fit into the 32-bit distance of the dll's seek
+    ///
+    /// The dll's seek only moves by a signed 32-bit distance, larger offsets are therefore split
+    /// into several relative seeks of at most `i32::MAX` bytes. The dll also only reports the low
+    /// 32 bits of the new position: for `SeekFrom::Start` the returned position is `pos` itself,
+    /// for `Current` and `End` it is the dll's report and therefore only correct below 4 GiB.
+    pub fn seek64(&self, pos: SeekFrom) -> Result<u64> {
+        let (move_method, mut remaining) = match pos {
+            SeekFrom::Start(n) if n > i64::max_value() as u64 => {
+                return Err(Error::new(ErrorKind::InvalidInput, "seek offset is too large"));
+            },
+            SeekFrom::Start(n) => (0, n as i64),
+            SeekFrom::Current(n) => (1, n),
+            SeekFrom::End(n) => (2, n),
+        };
+        let step = clamp_distance(remaining);
+        remaining -= step as i64;
+        let mut expected = match pos {
+            SeekFrom::Start(_) => Some(step as u64),
+            _ => None,
+        };
+        let mut position = self.seek_raw(step, move_method, expected)?;
+        while remaining != 0 {
+            let step = clamp_distance(remaining);
+            remaining -= step as i64;
+            expected = expected.map(|pos| pos + step as u64);
+            position = self.seek_raw(step, 1, expected)?;
+        }
+        Ok(expected.unwrap_or(position))
+    }
+
+    /// A single call of the dll's seek, the dll reports failure by returning -1
+    ///
+    /// -1 is also what the dll returns for a position whose low 32 bits are all set. If the caller
+    /// knows the `expected` position, -1 is taken as success when it matches that position.
+    fn seek_raw(&self, distance_to_move: i32, move_method: u32, expected: Option<u64>) -> Result<u64> {
+        match self.file_manager.seek(self, distance_to_move, move_method) {
+            -1 if expected.map(|pos| pos as u32) != Some(0xFFFF_FFFF) => {
+                Err(Error::new(ErrorKind::Other, "seek failed"))
+            },
+            pos => Ok(pos as u32 as u64),
+        }
+    }
+
+    /// Returns an iterator reading the file from the current position in chunks of `size` bytes
+    ///
+    /// Every chunk but the last one is exactly `size` bytes long, the iterator ends at the end of
+    /// the file or after yielding the first error.
+    pub fn chunks<'f>(&'f self, size: usize) -> ChunkIter<'f, 'a> {
+        ChunkIter {
+            file: self,
+            buf: vec![0u8; size.max(1)],
+            done: false,
+        }
+    }
+
+    /// Reads into `buf` from the current position following the contract of `Read::read`, the dll
+    /// doesn't need exclusive access to do that
+    ///
+    /// The dll reports success through its return value and the number of bytes through
+    /// `bytes_read`: a successful read of 0 bytes means the end of the file was reached and gives
+    /// `Ok(0)`, a failure code gives an error regardless of `bytes_read`.
+    fn read_shared(&self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = buf.len().min(i32::max_value() as usize);
         let mut bytes_read = 0;
-        self.file_manager.read(self, buf, len as i32, &mut bytes_read);
-        Ok(bytes_read as usize)
+        match self.file_manager.read(self, buf, len as i32, &mut bytes_read) {
+            0 => Err(GfxError::Failed { op: "read", code: 0 }.into()),
+            _ => Ok(bytes_read as usize),
+        }
+    }
+
+    /// Returns the name of the file, decoded with the manager's encoding
+    pub fn name(&self) -> Result<String> {
+        self.file_manager.file_name_from_handle(self)
+    }
+
+    /// Opens a new, independent handle to the same file
+    ///
+    /// Unlike `std::fs::File::try_clone` the two handles do not share their position, the new
+    /// handle starts at the beginning of the file. Each handle is closed separately when dropped.
+    /// The file is reopened with `Access::OpenExisting`.
+    pub fn try_clone(&self) -> Result<File<'a>> {
+        let name = self.name()?;
+        self.file_manager.open_file(&name, Access::OpenExisting, 0)
     }
+}
+
+fn clamp_distance(distance: i64) -> i32 {
+    distance.max(i32::min_value() as i64).min(i32::max_value() as i64) as i32
+}
+
+/// Iterator over the chunks of a file, see `File::chunks`
+pub struct ChunkIter<'f, 'a: 'f> {
+    file: &'f File<'a>,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<'f, 'a> Iterator for ChunkIter<'f, 'a> {
+    type Item = Result<Vec<u8>>;
 
-    #[inline]
-    unsafe fn initializer(&self) -> Initializer {
-        Initializer::nop()
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut filled = 0;
+        while filled < self.buf.len() {
+            match self.file.read_shared(&mut self.buf[filled..]) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                },
+                Ok(n) => filled += n,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+        if filled == 0 {
+            None
+        } else {
+            Some(Ok(self.buf[..filled].to_vec()))
+        }
+    }
+}
+
+impl<'a> Read for File<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.read_shared(buf)
     }
 }
 
 impl<'a> Write for File<'a> {
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.file_manager.check_writable()?;
         let len = buf.len();
         let mut bytes_written = 0;
         self.file_manager.write(self, buf, len as i32, &mut bytes_written);
@@ -80,25 +274,72 @@ impl<'a> Write for File<'a> {
 
 impl<'a> Seek for File<'a> {
     fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
-        let (move_method, distance_to_move) = {
-            match pos {
-                SeekFrom::Start(u) => {
-                    (0, u as i32)
-                },
-                SeekFrom::Current(i) => {
-                    (1, i as i32)
-                },
-                SeekFrom::End(i) => {
-                    (2, i as i32)
-                },
-            }
-        };
-        Ok(self.file_manager.seek(self, distance_to_move, move_method) as u64)
+        self.seek64(pos)
     }
 }
 
 impl<'a> Drop for File<'a> {
+    /// Closes the handle, unless it is invalid or the container it belongs to has been closed or
+    /// replaced in the meantime, in which case the handle is already gone
     fn drop(&mut self) {
-        self.file_manager.close_file(self);
+        self.file_manager.file_closed();
+        if self.is_valid() && self.generation == self.file_manager.generation() {
+            self.file_manager.close_file(self);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::io::{Read, SeekFrom};
+
+    use file_manager::{Access, GFXFileManager, Mode};
+
+    fn manager_with_file(data: &[u8]) -> GFXFileManager {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager.write_file("file", data).unwrap();
+        file_manager
+    }
+
+    #[test]
+    fn read_exactly_to_eof() {
+        let file_manager = manager_with_file(b"abcd");
+        let mut file = file_manager.open_file("file", Access::OpenExisting, 0).unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(file.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"abcd");
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn read_past_eof() {
+        let file_manager = manager_with_file(b"abcd");
+        let mut file = file_manager.open_file("file", Access::OpenExisting, 0).unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(file.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], b"abcd");
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn read_failure_mid_stream() {
+        let file_manager = manager_with_file(b"abcdefgh");
+        let mut file = file_manager.open_file("file", Access::OpenExisting, 0).unwrap();
+        file_manager.mock_fail_reads_after(1);
+        let mut buf = [0u8; 4];
+        assert_eq!(file.read(&mut buf).unwrap(), 4);
+        assert!(file.read(&mut buf).is_err());
+        assert!(file.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn seeking_from_the_start_reports_positions_past_4_gib() {
+        let file_manager = manager_with_file(b"abcd");
+        let file = file_manager.open_file("file", Access::OpenExisting, 0).unwrap();
+        assert_eq!(file.seek64(SeekFrom::Start(0xFFFF_FFFF)).unwrap(), 0xFFFF_FFFF);
+        assert_eq!(file.seek64(SeekFrom::Start(5 << 30)).unwrap(), 5 << 30);
+        assert_eq!(file.seek64(SeekFrom::Start(2)).unwrap(), 2);
     }
 }
@@ -1,6 +1,7 @@
-use winapi::{c_char, c_int, c_ulonglong};
-use winapi::SYSTEMTIME;
+use sys::{c_char, c_int, c_ulonglong};
+use sys::SYSTEMTIME;
 
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
 use ffi::GFXFMInfo;
 
 #[repr(C)]
@@ -18,6 +19,7 @@ pub struct GFXInfo {
     pub field_3: c_int,
 }
 
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
 impl GFXInfo {
     pub fn new(index: c_int) -> Self {
         unsafe {
@@ -0,0 +1,74 @@
+//! Translation of unix style globs into the dll's pattern syntax.
+//!
+//! The patterns accepted by `find_first_file` and `for_each_entry_in_container` follow the rules
+//! of the windows `FindFirstFile` function: `*` matches any number of characters and `?` matches
+//! a single character, everything else matches itself ignoring case. Patterns only apply to the
+//! names inside a single directory, there are no character classes, alternatives or escapes.
+
+use std::borrow::Cow;
+use std::io;
+
+/// Converts a unix style glob into a pattern the dll understands
+///
+/// `**` is collapsed into `*` since the dll's patterns never span directories. Character classes
+/// (`[...]`), alternatives (`{a,b}`), escapes and path separators can't be expressed and result
+/// in an `InvalidInput` error.
+pub fn translate_glob(pattern: &str) -> io::Result<Cow<str>> {
+    if let Some(c) = pattern.chars().find(|&c| "[]{}\\/".contains(c)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' in the glob {:?} is not supported by the dll's patterns", c, pattern),
+        ));
+    }
+    if !pattern.contains("**") {
+        return Ok(Cow::Borrowed(pattern));
+    }
+    let mut translated = String::with_capacity(pattern.len());
+    for c in pattern.chars() {
+        if c != '*' || !translated.ends_with('*') {
+            translated.push(c);
+        }
+    }
+    Ok(Cow::Owned(translated))
+}
+
+/// Matches `name` against a pattern in the dll's syntax the way the dll does, ignoring ascii case
+pub(crate) fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(&b'*'), _) => matches(&pattern[1..], name) || (!name.is_empty() && matches(pattern, &name[1..])),
+        (Some(&b'?'), Some(_)) => matches(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) => p.eq_ignore_ascii_case(n) && matches(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{matches, translate_glob};
+
+    #[test]
+    fn double_stars_collapse() {
+        assert_eq!(translate_glob("**").unwrap(), "*");
+        assert_eq!(translate_glob("a***b?.txt").unwrap(), "a*b?.txt");
+        assert_eq!(translate_glob("*.txt").unwrap(), "*.txt");
+    }
+
+    #[test]
+    fn unsupported_characters_are_rejected() {
+        for glob in &["[ab].txt", "{a,b}", "a\\b", "a/b", "]"] {
+            assert_eq!(translate_glob(glob).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn matching_ignores_ascii_case() {
+        assert!(matches(b"*.TXT", b"a.txt"));
+        assert!(matches(b"a?c", b"abc"));
+        assert!(!matches(b"a?c", b"ac"));
+        assert!(matches(b"*", b""));
+        assert!(!matches(b"*.txt", b"a.dat"));
+    }
+}
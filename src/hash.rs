@@ -0,0 +1,111 @@
+use std::io;
+use std::path::Path;
+
+#[cfg(feature = "sha256")]
+use sha2::{Digest, Sha256};
+
+use file_manager::GFXFileManager;
+
+/// The hash algorithms supported by `GFXFileManager::hash_file`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgo {
+    /// CRC-32 (IEEE), the digest is returned as 4 big-endian bytes
+    Crc32,
+    /// SHA-256, requires the `sha256` feature
+    #[cfg(feature = "sha256")]
+    Sha256,
+}
+
+enum Hasher {
+    Crc32(Crc32),
+    #[cfg(feature = "sha256")]
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Crc32 => Hasher::Crc32(Crc32::new()),
+            #[cfg(feature = "sha256")]
+            HashAlgo::Sha256 => Hasher::Sha256(Sha256::default()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match *self {
+            Hasher::Crc32(ref mut crc) => crc.update(data),
+            #[cfg(feature = "sha256")]
+            Hasher::Sha256(ref mut sha) => sha.input(data),
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Crc32(crc) => {
+                let sum = crc.finish();
+                vec![(sum >> 24) as u8, (sum >> 16) as u8, (sum >> 8) as u8, sum as u8]
+            },
+            #[cfg(feature = "sha256")]
+            Hasher::Sha256(sha) => sha.result().to_vec(),
+        }
+    }
+}
+
+/// Table driven CRC-32 using the reflected IEEE polynomial
+pub(crate) struct Crc32 {
+    table: [u32; 256],
+    crc: u32,
+}
+
+impl Crc32 {
+    pub(crate) fn new() -> Self {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut c = i as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            }
+            *entry = c;
+        }
+        Crc32 { table, crc: 0xFFFF_FFFF }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.crc = self.table[((self.crc ^ b as u32) & 0xFF) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl GFXFileManager {
+    /// Hashes the file at `path` with the given algorithm and returns the raw digest bytes
+    ///
+    /// The file is streamed through the hasher with a fixed size buffer, so this works for files
+    /// of any size.
+    pub fn hash_file<P: AsRef<Path>>(&self, path: P, algo: HashAlgo) -> io::Result<Vec<u8>> {
+        let mut hasher = Hasher::new(algo);
+        self.stream_file(path, |chunk| {
+            hasher.update(chunk);
+            Ok(())
+        })?;
+        Ok(hasher.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Crc32;
+
+    #[test]
+    fn crc32_check_value() {
+        let mut crc = Crc32::new();
+        assert_eq!(crc.finish(), 0);
+        crc.update(b"12345");
+        crc.update(b"6789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+}
@@ -1,24 +1,58 @@
-#![cfg(all(target_os = "windows", target_arch = "x86"))]
-#![feature(abi_thiscall, read_initializer, try_from)]
+#![cfg(any(all(target_os = "windows", target_arch = "x86"), feature = "mock"))]
+extern crate encoding_rs;
+#[cfg(windows)]
 extern crate winapi;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "sha256")]
+extern crate sha2;
+
+#[macro_use]
+mod sys;
 
 pub mod cjarchivefm;
 pub mod dialog;
+pub mod diff;
+pub mod encoding;
+pub mod error;
+pub mod file_cache;
 pub mod file_manager;
 pub mod gfxfile;
 pub mod gfxinfo;
+pub mod glob;
+pub mod hash;
+pub mod path;
 pub mod result_entry;
+mod retry;
+pub mod search_builder;
 pub mod search_result;
+pub mod stats;
+mod transfer;
+mod unwind;
+pub mod verify;
 
 pub use cjarchivefm::CJArchiveFm;
-pub use dialog::DialogData;
-pub use file_manager::{Access, CallbackState, GFXFileManager, Mode, UnknownPair};
-pub use file_manager::{ErrorHandler, ForEachCallback};
-pub use gfxfile::File;
+pub use dialog::{DialogConfig, DialogData, DialogResult};
+pub use diff::{DiffEntry, DiffKind};
+pub use encoding::Encoding;
+pub use error::{GfxError, OpenError};
+pub use file_cache::FileCache;
+pub use file_manager::{Access, CallbackState, GFXFileManager, Mode, ModuleHandle, UnknownPair};
+pub use file_manager::{ErrorHandler, ForEachCallback, INVALID_HANDLE};
+pub use gfxfile::{ChunkIter, File};
 pub use gfxinfo::GFXInfo;
+pub use glob::translate_glob;
+pub use hash::HashAlgo;
 pub use result_entry::{Entry, ResultEntry};
+pub use search_builder::{FoundEntry, SearchBuilder};
 pub use search_result::SearchResult;
+pub use stats::ContainerStats;
+pub use verify::{VerifyIssue, VerifyReason};
 
-pub use winapi::HWND;
+pub use sys::HWND;
 
+#[cfg(all(target_os = "windows", target_arch = "x86"))]
 mod ffi;
+#[cfg(feature = "mock")]
+mod mock;
@@ -0,0 +1,719 @@
+//! An in-memory fake of the dll's file manager object, used by `GFXFileManager::new_mock`.
+//!
+//! The fake provides its own vtable so every wrapper method works unchanged. Paths are stored
+//! relative to the container root using `\` as separator, matching is case-insensitive for
+//! search patterns but lookups are exact.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::CStr;
+use std::ptr::{copy_nonoverlapping, null_mut};
+
+use sys::{c_char, c_int, c_long, c_ulong, c_void};
+use sys::{DWORD, HMODULE, HWND, LPDWORD, LPFILETIME};
+
+use cjarchivefm::CJArchiveFm;
+use dialog::DialogData;
+use file_manager::{CallbackState, ErrorHandler, ForEachCallback, IFileManager, UnknownPair, VTable};
+use file_manager::{OBJECT_VERSION, VIRTUAL_PATH_BUF_LEN};
+use glob::matches;
+use result_entry::{Entry, ResultEntry};
+use search_result::GFXSearchResult;
+
+const INVALID_HANDLE: c_int = -1;
+
+static EMPTY_CSTR: [u8; 1] = [0];
+
+static MOCK_VTABLE: VTable = VTable {
+    mode,
+    config_set: unsupported_2,
+    config_get: unsupported_2,
+    create_container,
+    open_container,
+    close_container,
+    is_open,
+    close_all_files,
+    main_module_handle,
+    function_9: unsupported_1,
+    open_file_cj,
+    open_file,
+    function_12: unsupported_0,
+    function_13: unsupported_0,
+    create_file_cj,
+    create_file,
+    delete_file,
+    close_file,
+    read,
+    write,
+    cmd_line_path: empty_cstr,
+    cmd_line_exe: empty_cstr,
+    get_unknown,
+    set_unknown,
+    create_dir,
+    delete_dir,
+    reset_dir,
+    change_dir,
+    get_dir_name,
+    set_virtual_path,
+    get_virtual_path,
+    find_first_file,
+    find_next_file,
+    close_search_result,
+    file_name_from_handle,
+    get_file_size,
+    get_file_time,
+    set_file_time,
+    seek,
+    get_hwnd,
+    set_hwnd,
+    register_error_handler,
+    import_dir: unsupported_transfer,
+    import_file: unsupported_transfer,
+    export_dir: unsupported_transfer,
+    export_file: unsupported_transfer,
+    file_exists,
+    show_dialog,
+    for_each_entry_in_container,
+    update_current_dir: success_0,
+    function_50: unsupported_1,
+    get_version,
+    check_version,
+    lock: success_1,
+    unlock: success_0,
+};
+
+/// Layout compatible with `IFileManager`, the vtable pointer has to stay the first field
+#[repr(C)]
+pub(crate) struct MockFileManager {
+    vtable: *const VTable,
+    state: MockState,
+}
+
+impl MockFileManager {
+    pub(crate) fn new(mode: c_int) -> Box<Self> {
+        Box::new(MockFileManager {
+            vtable: &MOCK_VTABLE,
+            state: MockState {
+                mode,
+                open: false,
+                files: HashMap::new(),
+                dirs: HashSet::new(),
+                cwd: String::new(),
+                virtual_path: String::new(),
+                handles: HashMap::new(),
+                next_handle: 0,
+                searches: HashMap::new(),
+                next_search: 1,
+                hwnd: null_mut(),
+                unknown: UnknownPair::default(),
+                reads_until_failure: None,
+            },
+        })
+    }
+
+    pub(crate) fn as_ptr(&mut self) -> *mut IFileManager {
+        self as *mut MockFileManager as *mut IFileManager
+    }
+
+    /// Makes every `read` after the next `n` ones fail, to test how errors of the dll are handled
+    #[cfg(test)]
+    pub(crate) fn fail_reads_after(this: *mut IFileManager, n: usize) {
+        unsafe { state(this) }.reads_until_failure = Some(n);
+    }
+}
+
+struct MockHandle {
+    path: String,
+    pos: usize,
+}
+
+struct MockState {
+    mode: c_int,
+    open: bool,
+    /// File contents keyed by their full path
+    files: HashMap<String, Vec<u8>>,
+    /// Full paths of all directories except the root, which is the empty string
+    dirs: HashSet<String>,
+    cwd: String,
+    virtual_path: String,
+    handles: HashMap<c_int, MockHandle>,
+    next_handle: c_int,
+    /// Pending entries of running searches, keyed by the id stored in the search result
+    searches: HashMap<c_int, Vec<(Entry, String, c_int)>>,
+    next_search: c_int,
+    hwnd: HWND,
+    unknown: UnknownPair,
+    /// Number of reads that still succeed before `read` starts failing, `None` to never fail
+    reads_until_failure: Option<usize>,
+}
+
+impl MockState {
+    /// Resolves `path` against the current directory, handling `.`, `..` and both separators
+    fn resolve(&self, path: &str) -> String {
+        let mut parts: Vec<&str> = Vec::new();
+        if !path.starts_with('\\') && !path.starts_with('/') {
+            parts.extend(self.cwd.split('\\').filter(|p| !p.is_empty()));
+        }
+        for part in path.split(|c| c == '\\' || c == '/') {
+            match part {
+                "" | "." => (),
+                ".." => { parts.pop(); },
+                part => parts.push(part),
+            }
+        }
+        parts.join("\\")
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        path.is_empty() || self.dirs.contains(path)
+    }
+
+    fn parent_exists(&self, path: &str) -> bool {
+        self.is_dir(parent(path))
+    }
+
+    /// Lists the direct children of the directory `dir` sorted by name, directories first
+    fn children(&self, dir: &str) -> Vec<(Entry, String, c_int)> {
+        let mut dirs: Vec<_> = self.dirs.iter()
+            .filter(|path| parent(path) == dir)
+            .map(|path| (Entry::Directory, file_name(path).to_owned(), 0))
+            .collect();
+        dirs.sort_by(|a, b| a.1.cmp(&b.1));
+        let mut files: Vec<_> = self.files.iter()
+            .filter(|&(path, _)| parent(path) == dir)
+            .map(|(path, data)| (Entry::File, file_name(path).to_owned(), data.len() as c_int))
+            .collect();
+        files.sort_by(|a, b| a.1.cmp(&b.1));
+        dirs.extend(files);
+        dirs
+    }
+
+    fn open_handle(&mut self, path: String) -> c_int {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.handles.insert(handle, MockHandle { path, pos: 0 });
+        handle
+    }
+}
+
+fn parent(path: &str) -> &str {
+    path.rfind('\\').map(|i| &path[..i]).unwrap_or("")
+}
+
+fn file_name(path: &str) -> &str {
+    path.rfind('\\').map(|i| &path[i + 1..]).unwrap_or(path)
+}
+
+unsafe fn state<'a>(this: *mut IFileManager) -> &'a mut MockState {
+    &mut (*(this as *mut MockFileManager)).state
+}
+
+/// Reads a string argument, mapping every byte to the char of the same value
+///
+/// The dll treats names as plain bytes in whatever encoding the caller uses, this keeps them
+/// unchanged for `raw` to turn them back into the same bytes.
+unsafe fn str_arg(ptr: *const c_char) -> String {
+    CStr::from_ptr(ptr).to_bytes().iter().map(|&b| b as char).collect()
+}
+
+/// Turns a string read by `str_arg` back into the bytes it was read from
+fn raw(s: &str) -> Vec<u8> {
+    s.chars().map(|c| c as u8).collect()
+}
+
+/// Copies `s` nul-terminated into `buf` as it was read by `str_arg`, truncating it to `len` bytes
+/// including the terminator, and returns the number of bytes of `s` that were copied
+unsafe fn write_str(s: &str, buf: *mut c_char, len: usize) -> c_int {
+    if len == 0 {
+        return 0;
+    }
+    let s = raw(s);
+    let n = s.len().min(len - 1);
+    copy_nonoverlapping(s.as_ptr() as *const c_char, buf, n);
+    *buf.add(n) = 0;
+    n as c_int
+}
+
+thiscall! {
+    fn unsupported_0(_: *mut IFileManager) -> c_int { 0 }
+    fn unsupported_1(_: *mut IFileManager, _: c_int) -> c_int { 0 }
+    fn unsupported_2(_: *mut IFileManager, _: c_int, _: c_int) -> c_int { 0 }
+    fn success_0(_: *mut IFileManager) -> c_int { 1 }
+    fn success_1(_: *mut IFileManager, _: c_int) -> c_int { 1 }
+
+    fn unsupported_transfer(_: *mut IFileManager, _: *const c_char, _: *const c_char, _: *const c_char, _: bool) -> c_int {
+        0
+    }
+
+    fn empty_cstr(_: *mut IFileManager) -> *mut c_char {
+        EMPTY_CSTR.as_ptr() as *mut c_char
+    }
+
+    fn mode(this: *mut IFileManager) -> c_int {
+        unsafe { state(this) }.mode
+    }
+
+    fn create_container(this: *mut IFileManager, _: *const c_char, _: *const c_char) -> c_int {
+        let state = unsafe { state(this) };
+        state.files.clear();
+        state.dirs.clear();
+        state.handles.clear();
+        state.cwd.clear();
+        state.open = true;
+        1
+    }
+
+    fn open_container(this: *mut IFileManager, _: *const c_char, _: *const c_char, _: c_int) -> c_int {
+        let state = unsafe { state(this) };
+        state.cwd.clear();
+        state.open = true;
+        1
+    }
+
+    fn close_container(this: *mut IFileManager) -> c_int {
+        let state = unsafe { state(this) };
+        state.handles.clear();
+        state.open = false;
+        1
+    }
+
+    fn is_open(this: *mut IFileManager) -> c_int {
+        unsafe { state(this) }.open as c_int
+    }
+
+    fn close_all_files(this: *mut IFileManager) -> c_int {
+        let state = unsafe { state(this) };
+        let count = state.handles.len();
+        state.handles.clear();
+        count as c_int
+    }
+
+    fn main_module_handle(_: *mut IFileManager) -> HMODULE {
+        null_mut()
+    }
+
+    fn open_file_cj(_: *mut IFileManager, _: *mut CJArchiveFm, _: *const c_char, _: c_int, _: c_int) -> c_int {
+        INVALID_HANDLE
+    }
+
+    fn open_file(this: *mut IFileManager, filename: *const c_char, _: c_int, _: c_int) -> c_int {
+        let state = unsafe { state(this) };
+        let path = state.resolve(&unsafe { str_arg(filename) });
+        if !state.open || !state.files.contains_key(&path) {
+            return INVALID_HANDLE;
+        }
+        state.open_handle(path)
+    }
+
+    fn create_file_cj(_: *mut IFileManager, _: *mut CJArchiveFm, _: *const c_char, _: c_int) -> c_int {
+        INVALID_HANDLE
+    }
+
+    fn create_file(this: *mut IFileManager, filename: *const c_char, _: c_int) -> c_int {
+        let state = unsafe { state(this) };
+        let path = state.resolve(&unsafe { str_arg(filename) });
+        if !state.open || path.is_empty() || state.dirs.contains(&path) || !state.parent_exists(&path) {
+            return INVALID_HANDLE;
+        }
+        state.files.insert(path.clone(), Vec::new());
+        state.open_handle(path)
+    }
+
+    fn delete_file(this: *mut IFileManager, filename: *const c_char) -> c_int {
+        let state = unsafe { state(this) };
+        let path = state.resolve(&unsafe { str_arg(filename) });
+        state.files.remove(&path).is_some() as c_int
+    }
+
+    fn close_file(this: *mut IFileManager, handle: c_int) -> c_int {
+        unsafe { state(this) }.handles.remove(&handle).is_some() as c_int
+    }
+
+    fn read(this: *mut IFileManager, handle: c_int, buf: *mut c_char, len: c_int, bytes_read: *mut c_ulong) -> c_int {
+        let state = unsafe { state(this) };
+        if let Some(ref mut remaining) = state.reads_until_failure {
+            if *remaining == 0 {
+                return 0;
+            }
+            *remaining -= 1;
+        }
+        let handle = match state.handles.get_mut(&handle) {
+            Some(handle) => handle,
+            None => return 0,
+        };
+        let data = match state.files.get(&handle.path) {
+            Some(data) => data,
+            None => return 0,
+        };
+        let start = handle.pos.min(data.len());
+        let n = (data.len() - start).min(len.max(0) as usize);
+        unsafe {
+            copy_nonoverlapping(data[start..].as_ptr() as *const c_char, buf, n);
+            if !bytes_read.is_null() {
+                *bytes_read = n as c_ulong;
+            }
+        }
+        handle.pos = start + n;
+        1
+    }
+
+    fn write(this: *mut IFileManager, handle: c_int, buf: *const c_char, len: c_int, written: *mut c_ulong) -> c_int {
+        let state = unsafe { state(this) };
+        let handle = match state.handles.get_mut(&handle) {
+            Some(handle) => handle,
+            None => return 0,
+        };
+        let data = match state.files.get_mut(&handle.path) {
+            Some(data) => data,
+            None => return 0,
+        };
+        let len = len.max(0) as usize;
+        let end = handle.pos + len;
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        unsafe {
+            copy_nonoverlapping(buf as *const u8, data[handle.pos..].as_mut_ptr(), len);
+            if !written.is_null() {
+                *written = len as c_ulong;
+            }
+        }
+        handle.pos = end;
+        1
+    }
+
+    fn get_unknown(this: *mut IFileManager, unknown: *mut UnknownPair) -> *mut UnknownPair {
+        unsafe { *unknown = state(this).unknown };
+        unknown
+    }
+
+    fn set_unknown(this: *mut IFileManager, a: c_int, b: c_int) -> c_int {
+        unsafe { state(this) }.unknown = UnknownPair(a, b);
+        1
+    }
+
+    fn create_dir(this: *mut IFileManager, name: *const c_char) -> c_int {
+        let state = unsafe { state(this) };
+        let path = state.resolve(&unsafe { str_arg(name) });
+        if !state.open || path.is_empty() || state.files.contains_key(&path) || !state.parent_exists(&path) {
+            return 0;
+        }
+        state.dirs.insert(path);
+        1
+    }
+
+    fn delete_dir(this: *mut IFileManager, name: *const c_char) -> c_int {
+        let state = unsafe { state(this) };
+        let path = state.resolve(&unsafe { str_arg(name) });
+        if !state.dirs.remove(&path) {
+            return 0;
+        }
+        let prefix = format!("{}\\", path);
+        state.dirs.retain(|dir| !dir.starts_with(&prefix));
+        state.files.retain(|file, _| !file.starts_with(&prefix));
+        1
+    }
+
+    fn reset_dir(this: *mut IFileManager) -> c_int {
+        unsafe { state(this) }.cwd.clear();
+        1
+    }
+
+    fn change_dir(this: *mut IFileManager, name: *const c_char) -> c_int {
+        let state = unsafe { state(this) };
+        let path = state.resolve(&unsafe { str_arg(name) });
+        if !state.is_dir(&path) {
+            return 0;
+        }
+        state.cwd = path;
+        1
+    }
+
+    fn get_dir_name(this: *mut IFileManager, len: usize, buf: *mut c_char) -> c_int {
+        let state = unsafe { state(this) };
+        let name = format!("\\{}", state.cwd);
+        if raw(&name).len() >= len {
+            // report truncation by filling the whole buffer like the dll does
+            unsafe { write_str(&name, buf, len) };
+            return len as c_int;
+        }
+        unsafe { write_str(&name, buf, len) }
+    }
+
+    fn set_virtual_path(this: *mut IFileManager, path: *const c_char) -> c_int {
+        unsafe { state(this) }.virtual_path = unsafe { str_arg(path) };
+        1
+    }
+
+    fn get_virtual_path(this: *mut IFileManager, buf: *mut c_char) -> c_int {
+        unsafe { write_str(&state(this).virtual_path, buf, VIRTUAL_PATH_BUF_LEN) }
+    }
+
+    fn find_first_file(this: *mut IFileManager, search: *mut GFXSearchResult, pattern: *const c_char, entry: *mut ResultEntry) -> *mut GFXSearchResult {
+        let state = unsafe { state(this) };
+        let pattern = unsafe { str_arg(pattern) };
+        let (dir, pattern) = match pattern.rfind(|c| c == '\\' || c == '/') {
+            Some(i) => (state.resolve(&pattern[..i]), pattern[i + 1..].to_owned()),
+            None => (state.cwd.clone(), pattern),
+        };
+        let search = unsafe { &mut *search };
+        let mut found: Vec<_> = state.children(&dir).into_iter()
+            .filter(|&(_, ref name, _)| matches(pattern.as_bytes(), name.as_bytes()))
+            .collect();
+        if !state.open || found.is_empty() {
+            search.success = 0;
+            return null_mut();
+        }
+        found.reverse();
+        let (typ, name, size) = found.pop().unwrap();
+        unsafe { *entry = ResultEntry::new(typ, &raw(&name), size) };
+        search.success = 1;
+        search.field_4 = state.next_search;
+        state.searches.insert(state.next_search, found);
+        state.next_search += 1;
+        search
+    }
+
+    fn find_next_file(this: *mut IFileManager, search: *mut GFXSearchResult, entry: *mut ResultEntry) -> c_int {
+        let state = unsafe { state(this) };
+        let id = unsafe { (*search).field_4 };
+        match state.searches.get_mut(&id).and_then(|found| found.pop()) {
+            Some((typ, name, size)) => {
+                unsafe { *entry = ResultEntry::new(typ, &raw(&name), size) };
+                1
+            },
+            None => 0,
+        }
+    }
+
+    fn close_search_result(this: *mut IFileManager, search: *mut GFXSearchResult) -> c_int {
+        let id = unsafe { (*search).field_4 };
+        unsafe { state(this) }.searches.remove(&id).is_some() as c_int
+    }
+
+    fn file_name_from_handle(this: *mut IFileManager, handle: c_int, buf: *mut c_char, len: usize) -> c_int {
+        match unsafe { state(this) }.handles.get(&handle) {
+            Some(handle) => unsafe { write_str(&format!("\\{}", handle.path), buf, len) },
+            None => 0,
+        }
+    }
+
+    fn get_file_size(this: *mut IFileManager, handle: c_int, _: LPDWORD) -> c_int {
+        let state = unsafe { state(this) };
+        state.handles.get(&handle)
+            .and_then(|handle| state.files.get(&handle.path))
+            .map(|data| data.len() as c_int)
+            .unwrap_or(-1)
+    }
+
+    fn get_file_time(this: *mut IFileManager, handle: c_int, creation_time: LPFILETIME, last_write_time: LPFILETIME) -> bool {
+        if !unsafe { state(this) }.handles.contains_key(&handle) {
+            return false;
+        }
+        unsafe {
+            *creation_time = ::std::mem::zeroed();
+            *last_write_time = ::std::mem::zeroed();
+        }
+        true
+    }
+
+    fn set_file_time(this: *mut IFileManager, handle: c_int, _: LPFILETIME, _: LPFILETIME) -> bool {
+        unsafe { state(this) }.handles.contains_key(&handle)
+    }
+
+    fn seek(this: *mut IFileManager, handle: c_int, distance: c_long, move_method: DWORD) -> c_int {
+        let state = unsafe { state(this) };
+        let handle = match state.handles.get_mut(&handle) {
+            Some(handle) => handle,
+            None => return -1,
+        };
+        let len = state.files.get(&handle.path).map(|data| data.len()).unwrap_or(0) as i64;
+        let base = match move_method {
+            0 => 0,
+            1 => handle.pos as i64,
+            2 => len,
+            _ => return -1,
+        };
+        let pos = base + distance as i64;
+        if pos < 0 {
+            return -1;
+        }
+        handle.pos = pos as usize;
+        pos as c_int
+    }
+
+    fn get_hwnd(this: *mut IFileManager) -> HWND {
+        unsafe { state(this) }.hwnd
+    }
+
+    fn set_hwnd(this: *mut IFileManager, hwnd: HWND) -> c_int {
+        unsafe { state(this) }.hwnd = hwnd;
+        1
+    }
+
+    fn register_error_handler(_: *mut IFileManager, _: ErrorHandler) -> c_int {
+        1
+    }
+
+    fn file_exists(this: *mut IFileManager, name: *const c_char, _: c_int) -> c_int {
+        let state = unsafe { state(this) };
+        let path = state.resolve(&unsafe { str_arg(name) });
+        (state.files.contains_key(&path) || state.dirs.contains(&path)) as c_int
+    }
+
+    fn show_dialog(_: *mut IFileManager, _: *mut DialogData) -> c_int {
+        0
+    }
+
+    fn for_each_entry_in_container(this: *mut IFileManager, callback: ForEachCallback, filter: *const c_char, userstate: *mut c_void) -> c_int {
+        // collect everything up front so the callback can call back into the manager
+        fn collect(state: &MockState, dir: &str, filter: &[u8], out: &mut Vec<(CallbackState, Entry, String, c_int)>) {
+            for (typ, name, size) in state.children(dir) {
+                let path = if dir.is_empty() { name.clone() } else { format!("{}\\{}", dir, name) };
+                match typ {
+                    Entry::Directory => {
+                        out.push((CallbackState::EnterDir, Entry::Directory, name.clone(), 0));
+                        collect(state, &path, filter, out);
+                        out.push((CallbackState::LeaveDir, Entry::Directory, name, 0));
+                    },
+                    Entry::File => if matches(filter, name.as_bytes()) {
+                        out.push((CallbackState::File, Entry::File, name, size));
+                    },
+                }
+            }
+        }
+
+        let entries = {
+            let state = unsafe { state(this) };
+            if !state.open {
+                return 0;
+            }
+            let filter = unsafe { str_arg(filter) };
+            let mut entries = Vec::new();
+            collect(state, &state.cwd, filter.as_bytes(), &mut entries);
+            entries
+        };
+        for (callback_state, typ, name, size) in entries {
+            callback(callback_state as c_int, ResultEntry::new(typ, &raw(&name), size), userstate);
+        }
+        1
+    }
+
+    fn get_version(_: *mut IFileManager) -> c_int {
+        OBJECT_VERSION
+    }
+
+    fn check_version(_: *mut IFileManager, version: c_int) -> c_int {
+        (version == OBJECT_VERSION) as c_int
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, SeekFrom, Write};
+
+    use file_manager::{Access, GFXFileManager, Mode};
+
+    fn open_mock() -> GFXFileManager {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager
+    }
+
+    #[test]
+    fn nothing_works_without_a_container() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(!file_manager.is_open());
+        assert!(file_manager.create_file("file", 0).is_err());
+        assert!(file_manager.create_directory("dir").is_err());
+    }
+
+    #[test]
+    fn written_files_can_be_read_back() {
+        let file_manager = open_mock();
+        file_manager.write_file("file", b"hello").unwrap();
+        assert_eq!(file_manager.read_file("file").unwrap(), b"hello");
+        assert!(file_manager.open_file("missing", Access::OpenExisting, 0).is_err());
+    }
+
+    #[test]
+    fn lookups_are_exact() {
+        let file_manager = open_mock();
+        file_manager.write_file("file", b"hello").unwrap();
+        assert!(file_manager.exists("file"));
+        assert!(!file_manager.exists("FILE"));
+    }
+
+    #[test]
+    fn writes_and_seeks_share_the_position() {
+        let file_manager = open_mock();
+        let mut file = file_manager.create_file("file", 0).unwrap();
+        file.write_all(b"abcdef").unwrap();
+        assert_eq!(file.len(), 6);
+        assert_eq!(file.seek64(SeekFrom::Start(2)).unwrap(), 2);
+        file.write_all(b"XY").unwrap();
+        assert_eq!(file.seek64(SeekFrom::End(-1)).unwrap(), 5);
+        assert!(file.seek64(SeekFrom::Current(-10)).is_err());
+        file.rewind().unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"abXYef");
+    }
+
+    #[test]
+    fn paths_resolve_against_the_current_directory() {
+        let file_manager = open_mock();
+        file_manager.create_directory("dir").unwrap();
+        assert!(file_manager.create_directory("missing\\dir").is_err());
+        assert!(file_manager.change_directory("dir"));
+        assert_eq!(file_manager.get_directory_name().unwrap(), "\\dir");
+        file_manager.write_file("file", b"in dir").unwrap();
+        assert_eq!(file_manager.read_file("..\\dir/./file").unwrap(), b"in dir");
+        assert!(file_manager.change_directory("\\"));
+        assert!(file_manager.exists("dir\\file"));
+        assert!(!file_manager.change_directory("dir\\file"));
+    }
+
+    #[test]
+    fn deleting_a_directory_removes_its_contents() {
+        let file_manager = open_mock();
+        file_manager.create_directory("dir").unwrap();
+        file_manager.create_directory("dir\\sub").unwrap();
+        file_manager.write_file("dir\\sub\\file", b"data").unwrap();
+        file_manager.write_file("dir2", b"data").unwrap();
+        file_manager.delete_directory("dir").unwrap();
+        assert!(!file_manager.exists("dir\\sub"));
+        assert!(!file_manager.exists("dir\\sub\\file"));
+        assert!(file_manager.exists("dir2"));
+    }
+
+    #[test]
+    fn search_lists_directories_first_then_files_by_name() {
+        let file_manager = open_mock();
+        file_manager.write_file("b.txt", b"").unwrap();
+        file_manager.write_file("a.txt", b"").unwrap();
+        file_manager.write_file("c.dat", b"").unwrap();
+        file_manager.create_directory("z").unwrap();
+        let names: Vec<_> = file_manager.search("*").unwrap().map(|entry| entry.name().into_owned()).collect();
+        assert_eq!(names, ["z", "a.txt", "b.txt", "c.dat"]);
+        let names: Vec<_> = file_manager.search("*.TXT").unwrap().map(|entry| entry.name().into_owned()).collect();
+        assert_eq!(names, ["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn closing_the_container_closes_all_handles() {
+        let file_manager = open_mock();
+        file_manager.write_file("file", b"data").unwrap();
+        let file = file_manager.open_file("file", Access::OpenExisting, 0).unwrap();
+        assert_eq!(file_manager.open_file_count(), 1);
+        assert!(file_manager.close_container());
+        assert!(!file_manager.is_open());
+        assert_eq!(file.try_len().ok(), None);
+    }
+
+    #[test]
+    fn virtual_path_round_trips() {
+        let file_manager = open_mock();
+        assert!(file_manager.set_virtual_path("\\data"));
+        assert_eq!(file_manager.get_virtual_path().unwrap(), "\\data");
+    }
+}
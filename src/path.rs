@@ -0,0 +1,117 @@
+//! Helpers for building and splitting paths inside a container.
+//!
+//! The dll separates directories with a backslash and treats a leading backslash as the root of
+//! the container. All functions here also accept forward slashes so paths built with `/` work
+//! unchanged.
+
+use std::borrow::Cow;
+
+/// The directory separator used by the dll
+pub const SEPARATOR: char = '\\';
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+/// Converts every `/` into `SEPARATOR` and collapses runs of separators into one
+///
+/// A trailing separator is removed unless the path is just the root.
+pub fn normalize(path: &str) -> Cow<str> {
+    let clean = !path.contains('/')
+        && !path.contains("\\\\")
+        && (path.len() <= 1 || !path.ends_with(SEPARATOR));
+    if clean {
+        return Cow::Borrowed(path);
+    }
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        if is_separator(c) {
+            if !out.ends_with(SEPARATOR) {
+                out.push(SEPARATOR);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    if out.len() > 1 && out.ends_with(SEPARATOR) {
+        out.pop();
+    }
+    Cow::Owned(out)
+}
+
+/// Joins `rel` onto `base` with a single separator in between
+///
+/// If `rel` starts with a separator it is already rooted and replaces `base`, like
+/// `std::path::Path::join` does for absolute paths.
+pub fn join(base: &str, rel: &str) -> String {
+    if base.is_empty() || rel.starts_with(is_separator) {
+        return normalize(rel).into_owned();
+    }
+    normalize(&format!("{}{}{}", base, SEPARATOR, rel)).into_owned()
+}
+
+/// Returns the path without its last component, `None` for the root or an empty path
+///
+/// A single relative component has the empty path as parent.
+pub fn parent(path: &str) -> Option<&str> {
+    let path = path.trim_right_matches(is_separator);
+    if path.is_empty() {
+        return None;
+    }
+    match path.rfind(is_separator) {
+        Some(i) => match path[..i].trim_right_matches(is_separator) {
+            "" => Some(&path[..1]),
+            parent => Some(parent),
+        },
+        None => Some(""),
+    }
+}
+
+/// Returns the last component of the path, `None` for the root or an empty path
+pub fn file_name(path: &str) -> Option<&str> {
+    let path = path.trim_right_matches(is_separator);
+    let name = match path.rfind(is_separator) {
+        Some(i) => &path[i + 1..],
+        None => path,
+    };
+    if name.is_empty() { None } else { Some(name) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{file_name, join, normalize, parent};
+
+    #[test]
+    fn normalize_uses_single_backslashes() {
+        assert_eq!(normalize("a\\b"), "a\\b");
+        assert_eq!(normalize("a//b\\\\c/"), "a\\b\\c");
+        assert_eq!(normalize("/"), "\\");
+        assert_eq!(normalize(""), "");
+    }
+
+    #[test]
+    fn join_replaces_the_base_with_rooted_paths() {
+        assert_eq!(join("a", "b/c"), "a\\b\\c");
+        assert_eq!(join("a\\", "b"), "a\\b");
+        assert_eq!(join("", "b"), "b");
+        assert_eq!(join("a", "\\b"), "\\b");
+    }
+
+    #[test]
+    fn parent_keeps_the_root() {
+        assert_eq!(parent("\\a\\b"), Some("\\a"));
+        assert_eq!(parent("\\a"), Some("\\"));
+        assert_eq!(parent("//a/"), Some("/"));
+        assert_eq!(parent("a"), Some(""));
+        assert_eq!(parent("\\"), None);
+        assert_eq!(parent(""), None);
+    }
+
+    #[test]
+    fn file_name_is_the_last_component() {
+        assert_eq!(file_name("a\\b.txt"), Some("b.txt"));
+        assert_eq!(file_name("a/b/"), Some("b"));
+        assert_eq!(file_name("\\"), None);
+        assert_eq!(file_name(""), None);
+    }
+}
@@ -1,7 +1,10 @@
+use std::borrow::Cow;
 use std::ffi::CStr;
 
-use winapi::{c_char, c_int};
-use winapi::{FILETIME, WIN32_FIND_DATAA};
+use sys::{c_char, c_int};
+use sys::{FILETIME, WIN32_FIND_DATAA};
+
+use encoding::Encoding;
 
 pub enum Entry {
     Directory = 1,
@@ -35,6 +38,39 @@ pub struct ResultEntry {
 }
 
 impl ResultEntry {
+    /// Creates a zeroed entry for the dll to fill
+    pub(crate) fn empty() -> Self {
+        unsafe { ::std::mem::zeroed() }
+    }
+
+    /// Creates an entry as the dll would fill it, the name gets truncated to fit the buffer
+    #[cfg(feature = "mock")]
+    pub(crate) fn new(typ: Entry, name: &[u8], size: c_int) -> Self {
+        let mut entry = Self::empty();
+        entry.typ = typ as c_char;
+        entry.size = size;
+        let len = name.len().min(entry.filename.len() - 1);
+        for (dst, &src) in entry.filename.iter_mut().zip(name[..len].iter()) {
+            *dst = src as c_char;
+        }
+        entry
+    }
+
+    /// Returns the entry's name, replacing invalid utf8 sequences
+    ///
+    /// Names are stored in the container's encoding, use `name_in` with the manager's encoding for
+    /// containers that don't use utf8.
+    pub fn name(&self) -> Cow<str> {
+        let cstring = unsafe { CStr::from_ptr(self.filename.as_ptr()) };
+        cstring.to_string_lossy()
+    }
+
+    /// Returns the entry's name decoded with `encoding`, replacing invalid sequences
+    pub fn name_in(&self, encoding: Encoding) -> Cow<str> {
+        let cstring = unsafe { CStr::from_ptr(self.filename.as_ptr()) };
+        encoding.decode_lossy(cstring.to_bytes())
+    }
+
     pub fn filename(&self) -> Result<&str, ::std::str::Utf8Error> {
         let cstring = unsafe { CStr::from_ptr(self.filename.as_ptr()) };
         cstring.to_str()
@@ -44,6 +80,14 @@ impl ResultEntry {
         self.filename.as_ptr()
     }
 
+    pub fn is_directory(&self) -> bool {
+        self.typ == Entry::Directory as c_char
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.typ == Entry::File as c_char
+    }
+
     pub fn typ(&self) -> Entry {
         Entry::from(self.typ)
     }
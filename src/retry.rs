@@ -0,0 +1,104 @@
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use file_manager::GFXFileManager;
+
+const ERROR_SHARING_VIOLATION: i32 = 32;
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// Returns true for errors caused by another process briefly holding the file, like a virus
+/// scanner does
+fn is_transient(e: &io::Error) -> bool {
+    match e.raw_os_error() {
+        Some(ERROR_SHARING_VIOLATION) | Some(ERROR_LOCK_VIOLATION) => true,
+        _ => false,
+    }
+}
+
+/// Runs `op` until it succeeds, fails with a non-transient error or `retries` retries are used up,
+/// doubling the wait between two attempts starting at `backoff`
+///
+/// Only os errors the dll reported count as transient, errors raised before calling the dll, like
+/// an invalid path, end the loop right away.
+fn retry<F>(retries: u32, backoff: Duration, mut op: F) -> io::Result<()>
+    where F: FnMut() -> io::Result<()>
+{
+    let mut wait = backoff;
+    let mut attempt = 0;
+    loop {
+        let e = match op() {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if attempt >= retries || !is_transient(&e) {
+            return Err(e);
+        }
+        thread::sleep(wait);
+        wait *= 2;
+        attempt += 1;
+    }
+}
+
+impl GFXFileManager {
+    /// Like `import_directory`, but retries up to `retries` times while the failure is a sharing
+    /// or lock violation, waiting `backoff` before the first retry and twice as long before each
+    /// further one
+    pub fn import_directory_retry<P, Q, R>(&self, srcdir: P, dstdir: Q, dir_name: R, create_target_dir: bool, retries: u32, backoff: Duration) -> io::Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>
+    {
+        retry(retries, backoff, || self.import_directory(&srcdir, &dstdir, &dir_name, create_target_dir))
+    }
+
+    /// Like `import_file`, with the retry behaviour of `import_directory_retry`
+    pub fn import_file_retry<P, Q, R>(&self, srcdir: P, dstdir: Q, filename: R, create_target_dir: bool, retries: u32, backoff: Duration) -> io::Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>
+    {
+        retry(retries, backoff, || self.import_file(&srcdir, &dstdir, &filename, create_target_dir))
+    }
+
+    /// Like `export_directory`, with the retry behaviour of `import_directory_retry`
+    pub fn export_directory_retry<P, Q, R>(&self, srcdir: P, dstdir: Q, dir_name: R, create_target_dir: bool, retries: u32, backoff: Duration) -> io::Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>
+    {
+        retry(retries, backoff, || self.export_directory(&srcdir, &dstdir, &dir_name, create_target_dir))
+    }
+
+    /// Like `export_file`, with the retry behaviour of `import_directory_retry`
+    pub fn export_file_retry<P, Q, R>(&self, srcdir: P, dstdir: Q, filename: R, create_target_dir: bool, retries: u32, backoff: Duration) -> io::Result<()>
+        where P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>
+    {
+        retry(retries, backoff, || self.export_file(&srcdir, &dstdir, &filename, create_target_dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::time::Duration;
+
+    use super::{retry, ERROR_SHARING_VIOLATION};
+
+    #[test]
+    fn errors_raised_before_the_dll_are_not_retried() {
+        let mut calls = 0;
+        let res = retry(3, Duration::from_millis(0), || {
+            calls += 1;
+            Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid path"))
+        });
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn sharing_violations_are_retried() {
+        let mut calls = 0;
+        let res = retry(3, Duration::from_millis(0), || {
+            calls += 1;
+            if calls < 3 { Err(io::Error::from_raw_os_error(ERROR_SHARING_VIOLATION)) } else { Ok(()) }
+        });
+        assert!(res.is_ok());
+        assert_eq!(calls, 3);
+    }
+}
@@ -0,0 +1,110 @@
+use std::io;
+use std::vec;
+
+use file_manager::{DirGuard, GFXFileManager};
+use glob::matches;
+use path;
+
+/// An entry found by a `SearchBuilder`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundEntry {
+    /// The path of the entry, starting with the searched directory
+    pub path: String,
+    pub is_dir: bool,
+    /// Size in bytes, 0 for directories
+    pub size: u64,
+}
+
+/// Configures a search over one directory or a whole directory tree, created by
+/// `GFXFileManager::find`
+///
+/// Patterns use the dll's syntax, see the `glob` module. An entry is found if its name matches
+/// any of the patterns, so entries matching several patterns are only reported once. Without a
+/// pattern every entry matches.
+pub struct SearchBuilder<'a> {
+    file_manager: &'a GFXFileManager,
+    root: String,
+    patterns: Vec<String>,
+    recursive: bool,
+    directories: bool,
+}
+
+impl<'a> SearchBuilder<'a> {
+    /// Adds a pattern the names of found entries have to match
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        self.patterns.push(pattern.to_owned());
+        self
+    }
+
+    /// The directory to search in, the current directory by default
+    pub fn root(mut self, root: &str) -> Self {
+        self.root = root.to_owned();
+        self
+    }
+
+    /// Whether the subdirectories are searched as well, false by default
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Whether matching directories are reported besides files, false by default
+    pub fn directories(mut self, directories: bool) -> Self {
+        self.directories = directories;
+        self
+    }
+
+    /// Runs the search and returns the found entries
+    ///
+    /// The current directory is restored before this returns.
+    pub fn run(self) -> io::Result<vec::IntoIter<FoundEntry>> {
+        let entries: Vec<(String, bool, u64)> = if self.recursive {
+            self.file_manager
+                .entries_under(&self.root)?
+                .into_iter()
+                .map(|entry| (entry.path, entry.is_dir, entry.size))
+                .collect()
+        } else {
+            self.list_root()?
+        };
+        let found: Vec<_> = entries
+            .into_iter()
+            .filter(|&(_, is_dir, _)| self.directories || !is_dir)
+            .filter(|&(ref rel, _, _)| self.is_match(path::file_name(rel).unwrap_or("")))
+            .map(|(rel, is_dir, size)| FoundEntry { path: path::join(&self.root, &rel), is_dir, size })
+            .collect();
+        Ok(found.into_iter())
+    }
+
+    /// Returns `(name, is_dir, size)` of every entry directly inside the root
+    fn list_root(&self) -> io::Result<Vec<(String, bool, u64)>> {
+        let _guard = DirGuard::enter(self.file_manager, &self.root)?;
+        let search = match self.file_manager.search("*") {
+            Ok(search) => search,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(search
+            .map(|entry| (entry.name_in(self.file_manager.encoding()).into_owned(), entry.is_directory(), entry.size() as u64))
+            .filter(|&(ref name, _, _)| name != "." && name != "..")
+            .collect())
+    }
+
+    fn is_match(&self, name: &str) -> bool {
+        self.patterns.is_empty()
+            || self.patterns.iter().any(|pattern| matches(pattern.as_bytes(), name.as_bytes()))
+    }
+}
+
+impl GFXFileManager {
+    /// Starts building a search, see `SearchBuilder`
+    pub fn find(&self) -> SearchBuilder {
+        SearchBuilder {
+            file_manager: self,
+            root: String::new(),
+            patterns: Vec::new(),
+            recursive: false,
+            directories: false,
+        }
+    }
+}
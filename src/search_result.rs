@@ -1,14 +1,34 @@
-use winapi::{c_char, c_uchar, c_int};
-use winapi::HANDLE;
+use sys::{c_char, c_uchar, c_int};
+use sys::HANDLE;
 
 use file_manager::GFXFileManager;
+use result_entry::ResultEntry;
 
+/// A running search inside the container, iterating over the matched entries
 pub struct SearchResult<'a> {
-    inner: GFXSearchResult,
+    /// Boxed since the dll hands out pointers to this struct
+    inner: Box<GFXSearchResult>,
     file_manager: &'a GFXFileManager,
+    /// The entry returned by find_first_file that hasn't been yielded yet
+    first: Option<ResultEntry>,
+    done: bool,
 }
 
 impl<'a> SearchResult<'a> {
+    pub(crate) fn new(file_manager: &'a GFXFileManager) -> Self {
+        SearchResult {
+            inner: Box::new(unsafe { ::std::mem::zeroed() }),
+            file_manager,
+            first: None,
+            done: false,
+        }
+    }
+
+    /// Stores the entry returned by the initial find_first_file call to be yielded first
+    pub(crate) fn set_first(&mut self, entry: ResultEntry) {
+        self.first = Some(entry);
+    }
+
     pub(crate) fn inner_mut(&mut self) -> &mut GFXSearchResult {
         &mut self.inner
     }
@@ -22,6 +42,27 @@ impl<'a> SearchResult<'a> {
     }
 }
 
+impl<'a> Iterator for SearchResult<'a> {
+    type Item = ResultEntry;
+
+    fn next(&mut self) -> Option<ResultEntry> {
+        if let Some(entry) = self.first.take() {
+            return Some(entry);
+        }
+        if self.done {
+            return None;
+        }
+        let mut entry = ResultEntry::empty();
+        let file_manager = self.file_manager;
+        if file_manager.find_next_file(self, &mut entry) != 0 {
+            Some(entry)
+        } else {
+            self.done = true;
+            None
+        }
+    }
+}
+
 impl<'a> Drop for SearchResult<'a> {
     fn drop(&mut self) {
         self.file_manager.find_close(self.inner_mut());
@@ -31,8 +72,9 @@ impl<'a> Drop for SearchResult<'a> {
 #[repr(C)]
 #[allow(non_snake_case)]
 pub(crate) struct GFXSearchResult {
-    success: c_uchar,
-    field_4: c_int,
+    pub(crate) success: c_uchar,
+    /// Used by the mock file manager to identify its searches
+    pub(crate) field_4: c_int,
     field_8: c_int,
     field_C: c_int,
     field_10: c_int,
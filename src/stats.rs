@@ -0,0 +1,54 @@
+use std::io;
+
+use file_manager::{CallbackState, DirGuard, GFXFileManager};
+
+/// Summary of a container's content as returned by `GFXFileManager::stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContainerStats {
+    pub file_count: u64,
+    pub dir_count: u64,
+    /// Sum of the sizes of all files in bytes
+    pub total_bytes: u64,
+}
+
+impl GFXFileManager {
+    /// Walks the whole container and counts its files, directories and total file size
+    ///
+    /// The walk starts at the root regardless of the current directory, which is restored
+    /// afterwards.
+    pub fn stats(&self) -> io::Result<ContainerStats> {
+        let _guard = DirGuard::enter(self, "\\")?;
+        let mut stats = ContainerStats::default();
+        self.walk(|state, entry, _| {
+            match state {
+                CallbackState::EnterDir => stats.dir_count += 1,
+                CallbackState::File => {
+                    stats.file_count += 1;
+                    stats.total_bytes += entry.size() as u64;
+                },
+                _ => (),
+            }
+        })?;
+        Ok(stats)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use file_manager::{GFXFileManager, Mode};
+
+    use super::ContainerStats;
+
+    #[test]
+    fn stats_cover_the_whole_container_from_any_directory() {
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager.write_file("a", b"abc").unwrap();
+        file_manager.create_directory("sub").unwrap();
+        file_manager.write_file("sub\\b", b"de").unwrap();
+        assert!(file_manager.change_directory("sub"));
+        let expected = ContainerStats { file_count: 2, dir_count: 1, total_bytes: 5 };
+        assert_eq!(file_manager.stats().unwrap(), expected);
+        assert_eq!(file_manager.get_directory_name().unwrap(), "\\sub");
+    }
+}
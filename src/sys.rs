@@ -0,0 +1,90 @@
+//! The C and windows types used to talk to the dll.
+//!
+//! On windows they come from `winapi`. Other targets can only build the mock, so the types are
+//! defined here with the sizes they have on 32-bit windows, which keeps the layout of everything
+//! handed through the vtable the same as with the dll.
+
+/// Expands to a function or a function pointer type using the calling convention of the vtable
+///
+/// The dll's methods are `thiscall`, which only exists on x86. Other targets can only use the
+/// mock, whose functions use the C calling convention instead.
+#[cfg(target_arch = "x86")]
+macro_rules! thiscall {
+    (fn $args:tt $(-> $ret:ty)*) => { extern "thiscall" fn $args $(-> $ret)* };
+    ($(fn $name:ident $args:tt -> $ret:ty $body:block)*) => {
+        $(extern "thiscall" fn $name $args -> $ret $body)*
+    };
+}
+
+#[cfg(not(target_arch = "x86"))]
+macro_rules! thiscall {
+    (fn $args:tt $(-> $ret:ty)*) => { extern "C" fn $args $(-> $ret)* };
+    ($(fn $name:ident $args:tt -> $ret:ty $body:block)*) => {
+        $(extern "C" fn $name $args -> $ret $body)*
+    };
+}
+
+#[cfg(windows)]
+pub use winapi::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong, c_ulonglong, c_void};
+#[cfg(windows)]
+pub use winapi::{DWORD, FILETIME, HANDLE, HMODULE, HWND, LPDWORD, LPFILETIME, SYSTEMTIME, WIN32_FIND_DATAA};
+
+#[cfg(not(windows))]
+pub use self::windows_types::*;
+
+#[cfg(not(windows))]
+#[allow(non_camel_case_types, non_snake_case, clippy::upper_case_acronyms)]
+mod windows_types {
+    pub use std::os::raw::c_void;
+
+    pub type c_char = i8;
+    pub type c_uchar = u8;
+    pub type c_int = i32;
+    pub type c_long = i32;
+    pub type c_ulong = u32;
+    pub type c_ulonglong = u64;
+
+    pub type DWORD = c_ulong;
+    pub type LPDWORD = *mut DWORD;
+    pub type HANDLE = *mut c_void;
+    pub enum HINSTANCE__ {}
+    pub type HMODULE = *mut HINSTANCE__;
+    pub enum HWND__ {}
+    pub type HWND = *mut HWND__;
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct FILETIME {
+        pub dwLowDateTime: DWORD,
+        pub dwHighDateTime: DWORD,
+    }
+    pub type LPFILETIME = *mut FILETIME;
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct SYSTEMTIME {
+        pub wYear: u16,
+        pub wMonth: u16,
+        pub wDayOfWeek: u16,
+        pub wDay: u16,
+        pub wHour: u16,
+        pub wMinute: u16,
+        pub wSecond: u16,
+        pub wMilliseconds: u16,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct WIN32_FIND_DATAA {
+        pub dwFileAttributes: DWORD,
+        pub ftCreationTime: FILETIME,
+        pub ftLastAccessTime: FILETIME,
+        pub ftLastWriteTime: FILETIME,
+        pub nFileSizeHigh: DWORD,
+        pub nFileSizeLow: DWORD,
+        pub dwReserved0: DWORD,
+        pub dwReserved1: DWORD,
+        pub cFileName: [c_char; 260],
+        pub cAlternateFileName: [c_char; 14],
+    }
+}
@@ -0,0 +1,299 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+
+use file_manager::{same_path, Access, GFXFileManager, STREAM_BUF_LEN};
+use path;
+
+/// Converts a path relative to a container directory into one relative to `dest` on disk
+///
+/// Every component has to be a plain name, so an entry named `..` or one carrying a root or drive
+/// prefix can't make the path leave `dest`. Names containing a `:` are rejected on every platform,
+/// on windows they would name a drive or an alternate data stream.
+fn disk_path(dest: &Path, rel: &str) -> io::Result<PathBuf> {
+    let mut path = dest.to_path_buf();
+    for name in rel.split('\\') {
+        let mut components = Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) if !name.contains(':') => path.push(name),
+            _ => return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the entry {:?} would be extracted outside of {}", rel, dest.display()),
+            )),
+        }
+    }
+    Ok(path)
+}
+
+impl GFXFileManager {
+    /// Copies the file `src_path` of this container to `dst_path` in the container of `dst_fm`,
+    /// replacing it if it exists, and returns the number of bytes copied
+    ///
+    /// Each path is resolved relative to the current directory of its own manager. The content is
+    /// streamed with a bounded buffer and the creation and last write time are carried over.
+    /// Copying a file onto itself fails with `InvalidInput`, since creating the destination would
+    /// truncate the source before it is read.
+    pub fn copy_file_to<P, Q>(&self, src_path: P, dst_fm: &GFXFileManager, dst_path: Q) -> io::Result<u64>
+        where P: AsRef<Path>, Q: AsRef<Path>
+    {
+        if ptr::eq(self, dst_fm) && same_path(&self.absolute_path(&src_path)?, &self.absolute_path(&dst_path)?) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "can't copy a file onto itself"));
+        }
+        let mut src = self.open_file(src_path, Access::OpenExisting, 0)?;
+        let mut dst = dst_fm.create_file(dst_path, 0)?;
+        let mut buf = vec![0u8; STREAM_BUF_LEN];
+        let mut copied = 0;
+        loop {
+            let n = src.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            dst.write_all(&buf[..n])?;
+            copied += n as u64;
+        }
+        let (mut creation_time, mut last_write_time) = src.file_time()?;
+        dst.set_file_time(&mut creation_time, &mut last_write_time)?;
+        Ok(copied)
+    }
+
+    /// Streams the file at `path` into `out` and returns the number of bytes written
+    ///
+    /// The content is passed on with a bounded buffer, so nothing is held in memory beyond one
+    /// chunk. The file is closed before returning, also if `out` fails partway through.
+    pub fn copy_file_to_writer<P: AsRef<Path>, W: Write>(&self, path: P, out: &mut W) -> io::Result<u64> {
+        self.stream_file(path, |chunk| out.write_all(chunk))
+    }
+
+    /// Extracts the directory `root` of the container with all its content into `dest` on disk
+    /// and returns the number of bytes written
+    pub fn extract_to_disk<P: AsRef<Path>>(&self, root: &str, dest: P) -> io::Result<u64> {
+        self.extract_to_disk_with_progress(root, dest, |_, _, _| ())
+    }
+
+    /// Like `extract_to_disk`, but calls `progress` after every extracted file with the file's
+    /// path relative to `root`, the bytes extracted so far and the total number of bytes
+    ///
+    /// The callback runs on the caller's thread between two files and never inside a call into
+    /// the dll, so a panicking callback simply unwinds out of this function after the open
+    /// handles were closed and the current directory was restored.
+    pub fn extract_to_disk_with_progress<P, F>(&self, root: &str, dest: P, mut progress: F) -> io::Result<u64>
+        where P: AsRef<Path>, F: FnMut(&str, u64, u64)
+    {
+        let dest = dest.as_ref();
+        let entries = self.entries_under(root)?;
+        let total = entries.iter().map(|entry| entry.size).sum::<u64>();
+        let mut done = 0;
+        fs::create_dir_all(dest)?;
+        for entry in entries {
+            let target = disk_path(dest, &entry.path)?;
+            if entry.is_dir {
+                fs::create_dir_all(&target)?;
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut out = fs::File::create(&target)?;
+            done += self.stream_file(&path::join(root, &entry.path), |chunk| out.write_all(chunk))?;
+            progress(&entry.path, done, total);
+        }
+        Ok(done)
+    }
+
+    /// Like `extract_to_disk`, but writes the extracted files to disk using `threads` writer
+    /// threads
+    ///
+    /// Reading from the container stays on the calling thread since the dll isn't thread-safe,
+    /// only the writes to disk are spread over the writer threads. The file contents are handed
+    /// over through a bounded channel, so at most a few files are held in memory at once.
+    /// Returns the total number of bytes written or the first error that occurred.
+    pub fn extract_to_disk_parallel<P: AsRef<Path>>(&self, root: &str, dest: P, threads: usize) -> io::Result<u64> {
+        let dest = dest.as_ref();
+        let entries = self.entries_under(root)?;
+        let targets = entries.iter()
+            .map(|entry| disk_path(dest, &entry.path))
+            .collect::<io::Result<Vec<_>>>()?;
+        fs::create_dir_all(dest)?;
+        for (entry, target) in entries.iter().zip(&targets) {
+            if entry.is_dir {
+                fs::create_dir_all(target)?;
+            }
+        }
+
+        let threads = threads.max(1);
+        let (sender, receiver) = sync_channel::<(PathBuf, Vec<u8>)>(threads * 2);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || write_received(&receiver))
+            })
+            .collect();
+
+        let mut read_result = Ok(());
+        for (entry, target) in entries.into_iter().zip(targets).filter(|&(ref entry, _)| !entry.is_dir) {
+            let data = match self.read_file(&path::join(root, &entry.path)) {
+                Ok(data) => data,
+                Err(e) => {
+                    read_result = Err(e);
+                    break;
+                },
+            };
+            // a closed channel means every writer failed, their error is reported below
+            if sender.send((target, data)).is_err() {
+                break;
+            }
+        }
+        drop(sender);
+
+        let mut total = 0;
+        let mut write_result = Ok(());
+        for worker in workers {
+            match worker.join() {
+                Ok(Ok(written)) => total += written,
+                Ok(Err(e)) => if write_result.is_ok() { write_result = Err(e) },
+                Err(_) => if write_result.is_ok() {
+                    write_result = Err(io::Error::new(io::ErrorKind::Other, "a writer thread panicked"));
+                },
+            }
+        }
+        read_result.and(write_result).map(|_| total)
+    }
+
+    /// Creates a new container `dest` and copies every file and directory of this container into
+    /// it, leaving out the space deleted entries still occupy in the current archive
+    ///
+    /// The creation and last write time of every file are carried over. The dll offers no way to
+    /// set the time of a directory, so directories get the time they were created in `dest`.
+    #[cfg(all(target_os = "windows", target_arch = "x86"))]
+    pub fn compact(&self, dest: &str, password: &str) -> io::Result<()> {
+        let target = GFXFileManager::create(dest, password, self.mode())?;
+        for entry in self.entries_under("\\")? {
+            let entry_path = path::join("\\", &entry.path);
+            if entry.is_dir {
+                target.create_directory(&entry_path)?;
+            } else {
+                self.copy_file_to(&entry_path, &target, &entry_path)?;
+            }
+        }
+        target.commit()
+    }
+
+    /// Imports the directory `src` on disk with all its content into the directory `dest` of the
+    /// container and returns the number of bytes written
+    pub fn import_from_disk<P: AsRef<Path>>(&self, src: P, dest: &str) -> io::Result<u64> {
+        self.import_from_disk_with_progress(src, dest, |_, _, _| ())
+    }
+
+    /// Like `import_from_disk`, but calls `progress` after every imported file with the file's
+    /// path relative to `dest`, the bytes imported so far and the total number of bytes
+    ///
+    /// The same guarantees regarding panics as for `extract_to_disk_with_progress` apply.
+    pub fn import_from_disk_with_progress<P, F>(&self, src: P, dest: &str, mut progress: F) -> io::Result<u64>
+        where P: AsRef<Path>, F: FnMut(&str, u64, u64)
+    {
+        let mut entries = Vec::new();
+        collect_disk_entries(src.as_ref(), String::new(), &mut entries)?;
+        self.create_container_dir_all(dest)?;
+        let total = entries.iter().map(|&(_, _, size)| size).sum::<u64>();
+        let mut done = 0;
+        for (disk, rel, _) in entries {
+            let target = path::join(dest, &rel);
+            if disk.is_dir() {
+                if !self.exists(&target) {
+                    self.create_directory(&target)?;
+                }
+                continue;
+            }
+            let mut file = self.create_file(&target, 0)?;
+            done += io::copy(&mut fs::File::open(&disk)?, &mut file)?;
+            progress(&rel, done, total);
+        }
+        Ok(done)
+    }
+
+    /// Creates the container directory `dir` and all of its missing parents
+    fn create_container_dir_all(&self, dir: &str) -> io::Result<()> {
+        let dir = path::normalize(dir);
+        let mut current = String::new();
+        if dir.starts_with(path::SEPARATOR) {
+            current.push(path::SEPARATOR);
+        }
+        for name in dir.split(path::SEPARATOR).filter(|name| !name.is_empty()) {
+            current = path::join(&current, name);
+            if !self.exists(&current) {
+                self.create_directory(&current)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the files received through the channel until it is closed or a write fails, returns
+/// the number of bytes written
+fn write_received(receiver: &Mutex<Receiver<(PathBuf, Vec<u8>)>>) -> io::Result<u64> {
+    let mut written = 0;
+    loop {
+        let received = receiver.lock().unwrap().recv();
+        let (path, data) = match received {
+            Ok(file) => file,
+            Err(_) => return Ok(written),
+        };
+        fs::write(&path, &data)?;
+        written += data.len() as u64;
+    }
+}
+
+/// Recursively collects `(disk path, container relative path, size)` of everything below `dir`,
+/// directories are listed before their content
+fn collect_disk_entries(dir: &Path, rel: String, out: &mut Vec<(PathBuf, String, u64)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let rel = if rel.is_empty() { name } else { format!("{}\\{}", rel, name) };
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            out.push((entry.path(), rel.clone(), 0));
+            collect_disk_entries(&entry.path(), rel, out)?;
+        } else {
+            out.push((entry.path(), rel, metadata.len()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::disk_path;
+
+    #[test]
+    fn disk_paths_stay_inside_the_destination() {
+        let dest = Path::new("out");
+        assert_eq!(disk_path(dest, "a\\b.txt").unwrap(), dest.join("a").join("b.txt"));
+        for rel in &["..", "a\\..\\..\\b", ".", "C:", "C:\\b", "a\\D:b", "", "a\\\\b", "/etc"] {
+            assert!(disk_path(dest, rel).is_err(), "{:?} was accepted", rel);
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn copying_a_file_onto_itself_keeps_it() {
+        use file_manager::{GFXFileManager, Mode};
+
+        let file_manager = GFXFileManager::new_mock(Mode::CP);
+        assert!(file_manager.create_container("", ""));
+        file_manager.write_file("a", b"hello").unwrap();
+        assert!(file_manager.copy_file_to("a", &file_manager, "a").is_err());
+        assert!(file_manager.copy_file_to("a", &file_manager, "/a").is_err());
+        assert!(file_manager.copy_file_to("a", &file_manager, "x\\..\\.\\a").is_err());
+        assert_eq!(file_manager.read_file("a").unwrap(), b"hello");
+        assert_eq!(file_manager.copy_file_to("a", &file_manager, "b").unwrap(), 5);
+        assert_eq!(file_manager.read_file("b").unwrap(), b"hello");
+    }
+}
@@ -0,0 +1,40 @@
+//! Keeps panics of user callbacks from unwinding into the dll.
+//!
+//! Unwinding through the dll's frames is undefined behavior, so every callback the dll invokes
+//! runs its Rust code through a `PanicSlot`. A caught panic is stored and resumed once control is
+//! back in Rust, after the call into the dll returned.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+#[derive(Default)]
+pub(crate) struct PanicSlot {
+    payload: Option<Box<Any + Send>>,
+}
+
+impl PanicSlot {
+    /// Runs `f`, catching a panic and storing it instead of letting it unwind
+    ///
+    /// Once a panic was caught `f` isn't run anymore and `None` is returned, so the remaining
+    /// callbacks of the dll call become no-ops.
+    pub(crate) fn call<R, F: FnOnce() -> R>(&mut self, f: F) -> Option<R> {
+        if self.payload.is_some() {
+            return None;
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        match result {
+            Ok(value) => Some(value),
+            Err(payload) => {
+                self.payload = Some(payload);
+                None
+            },
+        }
+    }
+
+    /// Continues unwinding with the caught panic, if there is one
+    pub(crate) fn resume(self) {
+        if let Some(payload) = self.payload {
+            panic::resume_unwind(payload);
+        }
+    }
+}
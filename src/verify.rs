@@ -0,0 +1,52 @@
+use std::io::{self, Read};
+
+use file_manager::{Access, GFXFileManager, STREAM_BUF_LEN};
+
+/// A problem found by `GFXFileManager::verify`
+#[derive(Debug)]
+pub struct VerifyIssue {
+    pub path: String,
+    pub reason: VerifyReason,
+}
+
+#[derive(Debug)]
+pub enum VerifyReason {
+    /// The file couldn't be opened
+    Open(io::Error),
+    /// Reading the file failed after the given number of bytes
+    Read { bytes_read: u64, error: io::Error },
+    /// The file's content is shorter or longer than its stored size
+    SizeMismatch { expected: u64, actual: u64 },
+}
+
+impl GFXFileManager {
+    /// Reads every file of the container end to end and reports all files that couldn't be read
+    /// completely or whose content doesn't match their stored size
+    ///
+    /// An error is only returned if the container itself couldn't be traversed.
+    pub fn verify(&self) -> io::Result<Vec<VerifyIssue>> {
+        let mut issues = Vec::new();
+        let mut buf = vec![0u8; STREAM_BUF_LEN];
+        for entry in self.entries_under("\\")?.into_iter().filter(|entry| !entry.is_dir) {
+            let path = format!("\\{}", entry.path);
+            let reason = match self.open_file(&path, Access::OpenExisting, 0) {
+                Err(e) => Some(VerifyReason::Open(e)),
+                Ok(mut file) => {
+                    let mut bytes_read = 0;
+                    loop {
+                        match file.read(&mut buf) {
+                            Ok(0) if bytes_read == entry.size => break None,
+                            Ok(0) => break Some(VerifyReason::SizeMismatch { expected: entry.size, actual: bytes_read }),
+                            Ok(n) => bytes_read += n as u64,
+                            Err(error) => break Some(VerifyReason::Read { bytes_read, error }),
+                        }
+                    }
+                },
+            };
+            if let Some(reason) = reason {
+                issues.push(VerifyIssue { path, reason });
+            }
+        }
+        Ok(issues)
+    }
+}